@@ -3,6 +3,7 @@ use std::marker::PhantomData;
 use std::rc::Rc;
 
 use actix_http::body::{Body, MessageBody};
+use actix_http::http::StatusCode;
 use actix_http::{Extensions, PayloadStream, Request, Response};
 use actix_router::{Path, ResourceDef, ResourceInfo, Router, Url};
 use actix_service::{
@@ -15,6 +16,8 @@ use futures::{Async, Future, IntoFuture, Poll};
 use crate::helpers::{
     BoxedHttpNewService, BoxedHttpService, DefaultNewService, HttpDefaultNewService,
 };
+use crate::redirect::RedirectTarget;
+use crate::request::HttpRequest;
 use crate::resource::Resource;
 use crate::service::{ServiceRequest, ServiceResponse};
 use crate::state::{State, StateFactory, StateFactoryResult};
@@ -228,6 +231,35 @@ where
         self
     }
 
+    /// Set application level configuration.
+    ///
+    /// Configuration is stored in application extensions. Extractors and
+    /// services look it up during request processing and fall back to
+    /// the default value if configuration is not registered. Closure
+    /// receives previously registered value, so configuration could be
+    /// updated multiple times.
+    ///
+    /// ```rust
+    /// use actix_web2::{dev, App};
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .config(|cfg: &mut dev::ConnectionInfoConfig| {
+    ///             cfg.trusted_hops(1);
+    ///         });
+    /// }
+    /// ```
+    pub fn config<C, F>(mut self, f: F) -> Self
+    where
+        C: Default + 'static,
+        F: FnOnce(&mut C),
+    {
+        let mut cfg = self.extensions.remove::<C>().unwrap_or_default();
+        f(&mut cfg);
+        self.extensions.insert(cfg);
+        self
+    }
+
     /// Register a middleware.
     pub fn middleware<M, B1, F>(
         self,
@@ -261,11 +293,54 @@ where
             default: self.default,
             defaults: Vec::new(),
             factory_ref: self.factory_ref,
-            extensions: Extensions::new(),
+            extensions: self.extensions,
             _t: PhantomData,
         }
     }
 
+    /// Redirect requests of the `from` path to the `to` target with
+    /// *307 Temporary Redirect* response.
+    ///
+    /// Target may refer to path parameters of the `from` pattern, they are
+    /// substituted with the matched values. Substituted values are
+    /// percent-encoded, so a parameter can not turn a relative target
+    /// into a reference to another host. Request is rejected with
+    /// *400 Bad Request* if an empty parameter makes relative target
+    /// start with `//`.
+    ///
+    /// Panics if target refers to a parameter that is not defined by
+    /// `from` pattern, if target is neither a relative reference nor an
+    /// `http`/`https` url, or if target is a protocol-relative `//host`
+    /// reference.
+    ///
+    /// ```rust
+    /// use actix_web2::App;
+    ///
+    /// fn main() {
+    ///     let app = App::new()
+    ///         .redirect("/docs", "https://docs.rs/actix-web/")
+    ///         .redirect("/user/{id}/profile", "/users/{id}");
+    /// }
+    /// ```
+    pub fn redirect(self, from: &str, to: &str) -> Self {
+        self.redirect_with(from, to, StatusCode::TEMPORARY_REDIRECT)
+    }
+
+    /// Redirect requests of the `from` path to the `to` target with
+    /// *308 Permanent Redirect* response.
+    ///
+    /// See [`redirect()`](#method.redirect) for details.
+    pub fn permanent_redirect(self, from: &str, to: &str) -> Self {
+        self.redirect_with(from, to, StatusCode::PERMANENT_REDIRECT)
+    }
+
+    fn redirect_with(self, from: &str, to: &str, status: StatusCode) -> Self {
+        let target = RedirectTarget::new(from, to, status);
+        self.resource(from, move |r| {
+            r.to(move |req: HttpRequest| target.redirect(&req))
+        })
+    }
+
     /// Register an external resource.
     ///
     /// External resources are useful for URL generation purposes only
@@ -0,0 +1,249 @@
+//! Shared support of the binary serde formats, i.e. *MessagePack* and *CBOR*
+use std::fmt;
+use std::rc::Rc;
+
+use actix_http::dev::MessageBody;
+use actix_http::error::{Error, ErrorInternalServerError, PayloadError, ResponseError};
+use actix_http::http::StatusCode;
+use actix_http::Response;
+use bytes::Bytes;
+use futures::future::{err, ok, FutureResult};
+use futures::{Future, Stream};
+use mime::Mime;
+
+use crate::extractor::{content_type_matches, load_config};
+use crate::request::HttpRequest;
+use crate::service::ServiceRequest;
+
+/// A set of errors that can occur during parsing binary payloads
+///
+/// `E` is a deserialize error of the format.
+#[derive(Debug)]
+pub enum BinaryPayloadError<E> {
+    /// Payload size is bigger than allowed. (default: 256kB)
+    Overflow,
+    /// Content type error
+    ContentType,
+    /// Deserialize error
+    Deserialize(E),
+    /// Payload error
+    Payload(PayloadError),
+}
+
+impl<E: fmt::Display> fmt::Display for BinaryPayloadError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BinaryPayloadError::Overflow => {
+                write!(f, "Payload size is bigger than allowed.")
+            }
+            BinaryPayloadError::ContentType => write!(f, "Content type error"),
+            BinaryPayloadError::Deserialize(ref e) => {
+                write!(f, "Deserialize error: {}", e)
+            }
+            BinaryPayloadError::Payload(ref e) => {
+                write!(f, "Error that occur during reading payload: {}", e)
+            }
+        }
+    }
+}
+
+/// Return `BadRequest` for `BinaryPayloadError`
+impl<E: fmt::Debug + fmt::Display> ResponseError for BinaryPayloadError<E> {
+    fn error_response(&self) -> Response {
+        match *self {
+            BinaryPayloadError::Overflow => Response::new(StatusCode::PAYLOAD_TOO_LARGE),
+            _ => Response::new(StatusCode::BAD_REQUEST),
+        }
+    }
+}
+
+/// Binary payload extractor configuration
+///
+/// Configuration is selected by the deserialize error `E` of the format,
+/// use [**MsgPackConfig**](type.MsgPackConfig.html) and
+/// [**CborConfig**](type.CborConfig.html) aliases.
+pub struct BinaryConfig<E> {
+    limit: usize,
+    ehandler: Rc<Fn(BinaryPayloadError<E>, &HttpRequest) -> Error>,
+}
+
+impl<E> BinaryConfig<E> {
+    /// Change max size of payload. By default max size is 256Kb
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Set custom error handler
+    pub fn error_handler<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(BinaryPayloadError<E>, &HttpRequest) -> Error + 'static,
+    {
+        self.ehandler = Rc::new(f);
+        self
+    }
+}
+
+impl<E> Clone for BinaryConfig<E> {
+    fn clone(&self) -> Self {
+        BinaryConfig {
+            limit: self.limit,
+            ehandler: self.ehandler.clone(),
+        }
+    }
+}
+
+impl<E> Default for BinaryConfig<E>
+where
+    E: fmt::Debug + fmt::Display + 'static,
+{
+    fn default() -> Self {
+        BinaryConfig {
+            limit: 262_144,
+            ehandler: Rc::new(|e, _| e.into()),
+        }
+    }
+}
+
+/// Load request's payload and deserialize it with `decode`.
+///
+/// Content type of the request is checked with `accepts` predicate, size
+/// of the payload is limited by `BinaryConfig<E>` of the application.
+pub(crate) fn read_body<T, E, P, F>(
+    req: &mut ServiceRequest<P>,
+    accepts: fn(&Mime) -> bool,
+    decode: F,
+) -> Box<Future<Item = T, Error = Error>>
+where
+    T: 'static,
+    E: fmt::Debug + fmt::Display + 'static,
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+    F: FnOnce(&[u8]) -> Result<T, E> + 'static,
+{
+    let cfg = load_config::<BinaryConfig<E>>(req);
+
+    // check content-type
+    if !content_type_matches(req, accepts) {
+        return Box::new(err((*cfg.ehandler)(BinaryPayloadError::ContentType, req)));
+    }
+
+    let req2 = req.clone();
+    let ehandler = Rc::clone(&cfg.ehandler);
+    Box::new(
+        MessageBody::new(req)
+            .limit(cfg.limit)
+            .map_err(|e| match e {
+                PayloadError::Overflow => BinaryPayloadError::Overflow,
+                e => BinaryPayloadError::Payload(e),
+            })
+            .and_then(|body| decode(&body).map_err(BinaryPayloadError::Deserialize))
+            .map_err(move |e| (*ehandler)(e, &req2)),
+    )
+}
+
+/// Build *200 OK* response with serialized body
+pub(crate) fn respond<E>(
+    body: Result<Vec<u8>, E>,
+    content_type: &'static str,
+) -> FutureResult<Response, Error>
+where
+    E: fmt::Debug + fmt::Display + 'static,
+{
+    match body {
+        Ok(body) => ok(Response::build(StatusCode::OK)
+            .content_type(content_type)
+            .body(body)),
+        Err(e) => err(ErrorInternalServerError(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::body::{Body, ResponseBody};
+    use actix_http::http::header;
+    use actix_http::test::TestRequest;
+    use actix_http::Request;
+    use actix_service::{IntoNewService, NewService, Service};
+
+    use super::*;
+    use crate::service::ServiceResponse;
+    use crate::{App, FromRequest};
+
+    /// Format that accepts `application/octet-stream` utf-8 payloads
+    fn accepts(mime: &Mime) -> bool {
+        *mime == mime::APPLICATION_OCTET_STREAM
+    }
+
+    struct Text(String);
+
+    impl<P> FromRequest<P> for Text
+    where
+        P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+    {
+        type Error = Error;
+        type Future = Box<Future<Item = Self, Error = Error>>;
+
+        fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+            Box::new(
+                read_body(req, accepts, |body| {
+                    String::from_utf8(body.to_vec()).map_err(|e| e.utf8_error())
+                })
+                .map(Text),
+            )
+        }
+    }
+
+    fn service(
+    ) -> impl Service<Request = Request, Response = ServiceResponse, Error = ()> {
+        App::new()
+            .config(|cfg: &mut BinaryConfig<std::str::Utf8Error>| {
+                cfg.limit(8);
+            })
+            .resource("/", |r| r.to(|text: Text| text.0))
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap()
+    }
+
+    fn call(content_type: &str, body: &'static [u8]) -> (StatusCode, Bytes) {
+        let mut srv = service();
+        let req = TestRequest::with_header(header::CONTENT_TYPE, content_type)
+            .set_payload(Bytes::from_static(body))
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        let body = match res.response().body() {
+            ResponseBody::Body(Body::Bytes(b)) | ResponseBody::Other(Body::Bytes(b)) => {
+                b.clone()
+            }
+            _ => Bytes::new(),
+        };
+        (res.status(), body)
+    }
+
+    #[test]
+    fn test_read_body() {
+        assert_eq!(
+            call("application/octet-stream", b"hello"),
+            (StatusCode::OK, Bytes::from_static(b"hello"))
+        );
+        assert_eq!(call("text/plain", b"hello").0, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            call("application/octet-stream", b"\xff").0,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            call("application/octet-stream", b"hello world").0,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn test_binary_error() {
+        let e: BinaryPayloadError<String> = BinaryPayloadError::Overflow;
+        assert_eq!(e.error_response().status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let e = BinaryPayloadError::Deserialize("invalid".to_owned());
+        assert_eq!(e.error_response().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(e.to_string(), "Deserialize error: invalid");
+    }
+}
@@ -0,0 +1,185 @@
+use std::fmt;
+use std::ops::Deref;
+
+use actix_http::error::Error;
+use futures::future::{ok, Either, FutureResult};
+use futures::Future;
+
+use crate::handler::FromRequest;
+use crate::request::HttpRequest;
+use crate::service::ServiceRequest;
+
+/// Extractor wrapper that caches extracted value for the rest of
+/// the request's processing.
+///
+/// Value is stored in the request's extensions, keyed by the type.
+/// Subsequent `Cached<T>` extractions for the same request, i.e. from a
+/// middleware and then from a handler, return a clone of the stored value
+/// instead of running `T` extractor again. Errors are not cached.
+///
+/// ## Example
+///
+/// ```rust
+/// use actix_web2::{App, Cached, Error, FromRequest, ServiceRequest};
+/// use futures::future::{ok, FutureResult};
+///
+/// #[derive(Clone)]
+/// struct Identity {
+///     name: String,
+/// }
+///
+/// impl<P> FromRequest<P> for Identity {
+///     type Error = Error;
+///     type Future = FutureResult<Self, Error>;
+///
+///     fn from_request(_: &mut ServiceRequest<P>) -> Self::Future {
+///         // i.e. load user of the session from the database
+///         ok(Identity { name: "john".to_owned() })
+///     }
+/// }
+///
+/// /// `Identity` is loaded from the database only once per request
+/// fn index(user: Cached<Identity>) -> String {
+///     format!("Welcome {}!", user.name)
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/index.html", |r| r.get(index));
+/// }
+/// ```
+pub struct Cached<T>(T);
+
+/// Request extensions entry, wrapped to avoid collisions with values
+/// inserted by other means
+struct CachedValue<T>(T);
+
+impl<T> Cached<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Clone + 'static> Cached<T> {
+    /// Get cached value for the request, if it has already been extracted
+    pub fn get(req: &HttpRequest) -> Option<T> {
+        req.extensions()
+            .get::<CachedValue<T>>()
+            .map(|val| val.0.clone())
+    }
+}
+
+impl<T> Deref for Cached<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Cached<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T, P> FromRequest<P> for Cached<T>
+where
+    T: FromRequest<P> + Clone + 'static,
+    T::Future: 'static,
+{
+    type Error = Error;
+    type Future =
+        Either<FutureResult<Self, Error>, Box<Future<Item = Self, Error = Error>>>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        if let Some(val) = Cached::get(req) {
+            return Either::A(ok(Cached(val)));
+        }
+
+        let req2 = req.request().clone();
+        Either::B(Box::new(T::from_request(req).map_err(|e| e.into()).map(
+            move |val| {
+                req2.extensions_mut().insert(CachedValue(val.clone()));
+                Cached(val)
+            },
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use actix_http::error::ErrorBadRequest;
+    use actix_http::test::TestRequest;
+    use actix_http::{Extensions, HttpMessage, PayloadStream};
+    use actix_router::{Path, Url};
+    use futures::future::{err, FutureResult};
+
+    use super::*;
+
+    thread_local! {
+        static CALLS: Cell<usize> = Cell::new(0);
+    }
+
+    /// Extractor that counts its calls, fails if `x-fail` header is set
+    #[derive(Clone, Debug, PartialEq)]
+    struct Token(usize);
+
+    impl<P> FromRequest<P> for Token {
+        type Error = Error;
+        type Future = FutureResult<Token, Error>;
+
+        fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+            let calls = CALLS.with(|calls| {
+                calls.set(calls.get() + 1);
+                calls.get()
+            });
+            if req.headers().contains_key("x-fail") {
+                err(ErrorBadRequest("fail"))
+            } else {
+                ok(Token(calls))
+            }
+        }
+    }
+
+    fn service_request(req: TestRequest) -> ServiceRequest<PayloadStream> {
+        let req = req.finish();
+        let path = Path::new(Url::new(req.uri().clone()));
+        ServiceRequest::new(path, req, Rc::new(Extensions::new()))
+    }
+
+    #[test]
+    fn test_cached() {
+        let mut req = service_request(TestRequest::default());
+        assert!(Cached::<Token>::get(&req).is_none());
+
+        let token = Cached::<Token>::from_request(&mut req).wait().unwrap();
+        assert_eq!(*token, Token(1));
+        assert_eq!(Cached::<Token>::get(&req), Some(Token(1)));
+
+        // second extraction returns cached value
+        let token = Cached::<Token>::from_request(&mut req).wait().unwrap();
+        assert_eq!(token.into_inner(), Token(1));
+        assert_eq!(CALLS.with(|calls| calls.get()), 1);
+
+        // cache is per request
+        let mut req = service_request(TestRequest::default());
+        let token = Cached::<Token>::from_request(&mut req).wait().unwrap();
+        assert_eq!(*token, Token(2));
+    }
+
+    #[test]
+    fn test_cached_error() {
+        let mut req = service_request(TestRequest::with_header("x-fail", "1"));
+        assert!(Cached::<Token>::from_request(&mut req).wait().is_err());
+        assert!(Cached::<Token>::get(&req).is_none());
+
+        // errors are not cached, extractor runs again
+        assert!(Cached::<Token>::from_request(&mut req).wait().is_err());
+        assert_eq!(CALLS.with(|calls| calls.get()), 2);
+    }
+}
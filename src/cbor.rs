@@ -0,0 +1,164 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use actix_http::error::{Error, PayloadError};
+use actix_http::Response;
+use bytes::Bytes;
+use futures::future::FutureResult;
+use futures::{Future, Stream};
+use mime::Mime;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_cbor;
+
+use crate::binary::{read_body, respond, BinaryConfig, BinaryPayloadError};
+use crate::handler::FromRequest;
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+use crate::service::ServiceRequest;
+
+/// CBOR helper
+///
+/// Works the same way as [**Json**](struct.Json.html), but uses *CBOR*
+/// (RFC 7049) binary format. Requires `cbor` feature.
+///
+/// To extract typed information from request's body, the type `T` must
+/// implement the `Deserialize` trait from *serde*. Request's content type
+/// must be `application/cbor` or use `+cbor` suffix.
+///
+/// [**CborConfig**](dev/type.CborConfig.html) allows to configure
+/// extraction process.
+///
+/// ## Example
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{App, Cbor};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// echo `Info` back as `application/cbor` response
+/// fn index(info: Cbor<Info>) -> Cbor<Info> {
+///     info
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/index.html", |r| r.post(index));
+/// }
+/// ```
+pub struct Cbor<T>(pub T);
+
+impl<T> Cbor<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Cbor<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Cbor<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for Cbor<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cbor: {:?}", self.0)
+    }
+}
+
+impl<T> fmt::Display for Cbor<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl<T: Serialize> Responder for Cbor<T> {
+    type Error = Error;
+    type Future = FutureResult<Response, Error>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        respond(serde_cbor::to_vec(&self.0), "application/cbor")
+    }
+}
+
+impl<T, P> FromRequest<P> for Cbor<T>
+where
+    T: DeserializeOwned + 'static,
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Error = Error;
+    type Future = Box<Future<Item = Self, Error = Error>>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        Box::new(
+            read_body(req, is_cbor, |body| serde_cbor::from_slice::<T>(body)).map(Cbor),
+        )
+    }
+}
+
+fn is_cbor(mime: &Mime) -> bool {
+    mime.type_() == mime::APPLICATION
+        && (mime.subtype() == "cbor" || mime.suffix().map_or(false, |s| s == "cbor"))
+}
+
+/// A set of errors that can occur during parsing CBOR payloads
+pub type CborPayloadError = BinaryPayloadError<serde_cbor::error::Error>;
+
+/// Cbor extractor configuration
+///
+/// ```rust
+/// use actix_http::error;
+/// use actix_web2::{dev, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::CborConfig| {
+///         cfg.limit(4096)   // <- change cbor extractor configuration
+///            .error_handler(|err, _| {  // <- create custom error response
+///                error::InternalError::from_response(
+///                    err, HttpResponse::Conflict().finish()).into()
+///            });
+///     });
+/// }
+/// ```
+pub type CborConfig = BinaryConfig<serde_cbor::error::Error>;
+
+#[cfg(test)]
+mod tests {
+    use actix_http::error::ResponseError;
+    use actix_http::http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_cbor_content_type() {
+        assert!(is_cbor(&"application/cbor".parse().unwrap()));
+        assert!(is_cbor(&"application/senml+cbor".parse().unwrap()));
+        assert!(!is_cbor(&mime::APPLICATION_JSON));
+    }
+
+    #[test]
+    fn test_cbor_error() {
+        let e = serde_cbor::from_slice::<String>(b"\xc1").unwrap_err();
+        let e: CborPayloadError = BinaryPayloadError::Deserialize(e);
+        assert_eq!(e.error_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -4,10 +4,13 @@ use std::{fmt, str};
 
 use bytes::Bytes;
 use encoding::all::UTF_8;
-use encoding::types::{DecoderTrap, Encoding};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::types::{DecoderTrap, Encoding, EncodingRef};
 use futures::future::{err, ok, Either, FutureResult};
-use futures::{future, Async, Future, IntoFuture, Poll, Stream};
+use futures::{future, Async, Future, Poll, Stream};
 use mime::Mime;
+use percent_encoding::percent_decode;
+use serde::de::value::MapDeserializer;
 use serde::de::{self, DeserializeOwned};
 use serde::Serialize;
 use serde_json;
@@ -15,16 +18,19 @@ use serde_urlencoded;
 
 use actix_http::dev::{JsonBody, MessageBody, UrlEncoded};
 use actix_http::error::{
-    Error, ErrorBadRequest, ErrorNotFound, JsonPayloadError, PayloadError,
-    UrlencodedError,
+    Error, ErrorBadRequest, ErrorNotFound, ErrorUnsupportedMediaType, JsonPayloadError,
+    PayloadError, ResponseError, UrlencodedError,
 };
-use actix_http::http::StatusCode;
-use actix_http::{HttpMessage, Response};
+use actix_http::http::header::{HeaderValue, CONTENT_TYPE};
+use actix_http::http::{HeaderMap, StatusCode};
+use actix_http::{h1, HttpMessage, Payload, Response};
 use actix_router::PathDeserializer;
 
 use crate::handler::FromRequest;
+use crate::params;
+use crate::query::{self, ValueDeserializer};
 use crate::request::HttpRequest;
-use crate::responder::Responder;
+use crate::responder::{self, Responder};
 use crate::service::ServiceRequest;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
@@ -111,7 +117,25 @@ impl<T> Path<T> {
     }
 
     /// Extract path information from a request
+    ///
+    /// Percent-encoded parameters are decoded according to
+    /// [**PathConfig**](dev/struct.PathConfig.html).
     pub fn extract<P>(req: &ServiceRequest<P>) -> Result<Path<T>, de::value::Error>
+    where
+        T: DeserializeOwned,
+    {
+        let cfg = load_config::<PathConfig>(req);
+        if !cfg.decode {
+            return Self::extract_raw(req);
+        }
+
+        let params = params::decode(req.match_info(), cfg.encoded_slash)?;
+        params::from_params(params).map(|inner| Path { inner })
+    }
+
+    /// Extract path information from a request without percent-decoding
+    /// of the parameters.
+    pub fn extract_raw<P>(req: &ServiceRequest<P>) -> Result<Path<T>, de::value::Error>
     where
         T: DeserializeOwned,
     {
@@ -135,7 +159,70 @@ where
 
     #[inline]
     fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
-        Self::extract(req).map_err(ErrorNotFound).into_future()
+        match Self::extract(req) {
+            Ok(val) => ok(val),
+            Err(e) => {
+                let cfg = load_config::<PathConfig>(req);
+                err((*cfg.ehandler)(e, req))
+            }
+        }
+    }
+}
+
+/// Path extractor configuration
+///
+/// By default percent-encoded path parameters are decoded, i.e.
+/// `/users/john%20doe` is extracted as `john doe`. Raw value of a parameter
+/// is available with `HttpRequest::match_info()` or `Path::extract_raw()`.
+///
+/// ```rust
+/// use actix_web2::{dev, App};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::PathConfig| {
+///         cfg.encoded_slash(true);  // <- allow `%2F` in parameters
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct PathConfig {
+    decode: bool,
+    encoded_slash: bool,
+    ehandler: Rc<Fn(de::value::Error, &HttpRequest) -> Error>,
+}
+
+impl PathConfig {
+    /// Enable or disable percent-decoding of path parameters.
+    /// By default parameters are decoded.
+    pub fn decode(&mut self, decode: bool) -> &mut Self {
+        self.decode = decode;
+        self
+    }
+
+    /// Allow encoded `/` (`%2F`) in path parameters. By default
+    /// parameter with encoded `/` is rejected.
+    pub fn encoded_slash(&mut self, allow: bool) -> &mut Self {
+        self.encoded_slash = allow;
+        self
+    }
+
+    /// Set custom error handler
+    pub fn error_handler<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(de::value::Error, &HttpRequest) -> Error + 'static,
+    {
+        self.ehandler = Rc::new(f);
+        self
+    }
+}
+
+impl Default for PathConfig {
+    fn default() -> Self {
+        PathConfig {
+            decode: true,
+            encoded_slash: false,
+            ehandler: Rc::new(|e, _| ErrorNotFound(e)),
+        }
     }
 }
 
@@ -221,9 +308,17 @@ where
 
     #[inline]
     fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
-        serde_urlencoded::from_str::<T>(req.query_string())
-            .map(|val| ok(Query(val)))
-            .unwrap_or_else(|e| err(e.into()))
+        let cfg = load_config::<QueryConfig>(req);
+
+        let res = if cfg.nested {
+            query::from_str::<T>(req.query_string())
+        } else {
+            serde_urlencoded::from_str::<T>(req.query_string())
+        };
+        match res {
+            Ok(val) => ok(Query(val)),
+            Err(e) => err((*cfg.ehandler)(e, req)),
+        }
     }
 }
 
@@ -239,6 +334,75 @@ impl<T: fmt::Display> fmt::Display for Query<T> {
     }
 }
 
+/// Query extractor configuration
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_http::error;
+/// use actix_web2::{dev, App, HttpResponse, Query};
+///
+/// #[derive(Deserialize)]
+/// struct Filter {
+///     status: String,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Params {
+///     tag: Vec<String>,
+///     filter: Filter,
+/// }
+///
+/// /// accepts `/index.html?tag=a&tag=b&filter[status]=open`
+/// fn index(params: Query<Params>) -> String {
+///     format!("Tags {:?}, status {}", params.tag, params.filter.status)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .config(|cfg: &mut dev::QueryConfig| {
+///             cfg.nested(true)   // <- enable repeated keys and nested structures
+///                 .error_handler(|err, _| {  // <- create custom error response
+///                     error::InternalError::from_response(
+///                         err, HttpResponse::Conflict().finish()).into()
+///                 });
+///         })
+///         .resource("/index.html", |r| r.get(index));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct QueryConfig {
+    nested: bool,
+    ehandler: Rc<Fn(de::value::Error, &HttpRequest) -> Error>,
+}
+
+impl QueryConfig {
+    /// Support repeated keys and bracket syntax for nested structures,
+    /// i.e. `tag=a&tag=b&filter[status]=open`. By default query string is
+    /// parsed as flat `application/x-www-form-urlencoded` data.
+    pub fn nested(&mut self, nested: bool) -> &mut Self {
+        self.nested = nested;
+        self
+    }
+
+    /// Set custom error handler
+    pub fn error_handler<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(de::value::Error, &HttpRequest) -> Error + 'static,
+    {
+        self.ehandler = Rc::new(f);
+        self
+    }
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        QueryConfig {
+            nested: false,
+            ehandler: Rc::new(|e, _| e.into()),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 /// Extract typed information from the request's body.
 ///
@@ -301,17 +465,87 @@ where
 
     #[inline]
     fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
-        let cfg = FormConfig::default();
+        let cfg = load_config::<FormConfig>(req);
+
+        // check content-type
+        if !content_type_matches(req, |mime| cfg.accepts(mime)) {
+            return Box::new(err(if cfg.strict {
+                unsupported_media_type(req)
+            } else {
+                (*cfg.ehandler)(UrlencodedError::ContentType, req)
+            }));
+        }
+
+        // check charset, use default one if request does not declare it
+        let declared = match req.mime_type() {
+            Ok(Some(mime)) => mime.get_param(mime::CHARSET).is_some(),
+            _ => false,
+        };
+        let encoding = if declared {
+            match req.encoding() {
+                Ok(enc) => enc,
+                Err(_) => {
+                    return Box::new(err((*cfg.ehandler)(
+                        UrlencodedError::ContentType,
+                        req,
+                    )));
+                }
+            }
+        } else {
+            cfg.encoding
+        };
 
         let req2 = req.clone();
-        let err = Rc::clone(&cfg.ehandler);
-        Box::new(
-            UrlEncoded::new(req)
-                .limit(cfg.limit)
-                .map_err(move |e| (*err)(e, &req2))
-                .map(Form),
-        )
+        let ehandler = Rc::clone(&cfg.ehandler);
+        let enc: *const Encoding = encoding as *const Encoding;
+        let fut: Box<Future<Item = T, Error = UrlencodedError>> = if enc != UTF_8 {
+            // `UrlEncoded` decodes percent-encoded bytes as utf-8
+            Box::new(
+                MessageBody::new(req)
+                    .limit(cfg.limit)
+                    .map_err(|e| match e {
+                        PayloadError::Overflow => UrlencodedError::Overflow,
+                        e => UrlencodedError::Payload(e),
+                    })
+                    .and_then(move |body| decode_form::<T>(&body, encoding)),
+            )
+        } else if content_type_matches(req, is_form) {
+            Box::new(UrlEncoded::new(req).limit(cfg.limit))
+        } else {
+            let mut req = Retyped::new(req, "application/x-www-form-urlencoded");
+            Box::new(UrlEncoded::new(&mut req).limit(cfg.limit))
+        };
+        Box::new(fut.map_err(move |e| (*ehandler)(e, &req2)).map(Form))
+    }
+}
+
+/// Deserialize urlencoded payload. Percent-decoded names and values
+/// are decoded with the given charset.
+fn decode_form<T: DeserializeOwned>(
+    body: &[u8],
+    encoding: EncodingRef,
+) -> Result<T, UrlencodedError> {
+    let decode = |s: &[u8]| {
+        let s: Vec<u8> = s
+            .iter()
+            .map(|b| if *b == b'+' { b' ' } else { *b })
+            .collect();
+        let s: Vec<u8> = percent_decode(&s).collect();
+        encoding
+            .decode(&s, DecoderTrap::Strict)
+            .map_err(|_| UrlencodedError::Parse)
+    };
+
+    let mut pairs = Vec::new();
+    for pair in body.split(|b| *b == b'&').filter(|pair| !pair.is_empty()) {
+        let mut parts = pair.splitn(2, |b| *b == b'=');
+        let name = decode(parts.next().unwrap_or(b""))?;
+        let value = decode(parts.next().unwrap_or(b""))?;
+        pairs.push((name, ValueDeserializer(value)));
     }
+
+    T::deserialize(MapDeserializer::new(pairs.into_iter()))
+        .map_err(|_: de::value::Error| UrlencodedError::Parse)
 }
 
 impl<T: fmt::Debug> fmt::Debug for Form<T> {
@@ -355,8 +589,12 @@ impl<T: fmt::Display> fmt::Display for Form<T> {
 ///     );
 /// }
 /// ```
+#[derive(Clone)]
 pub struct FormConfig {
     limit: usize,
+    encoding: EncodingRef,
+    strict: bool,
+    ctype: Option<Rc<Fn(&Mime) -> bool>>,
     ehandler: Rc<Fn(UrlencodedError, &HttpRequest) -> Error>,
 }
 
@@ -367,6 +605,40 @@ impl FormConfig {
         self
     }
 
+    /// Set charset for requests that do not declare one, i.e. `iso-8859-1`.
+    /// By default *UTF-8* is used.
+    ///
+    /// Panics if charset label is unknown.
+    pub fn charset(&mut self, label: &str) -> &mut Self {
+        self.encoding = encoding_from_whatwg_label(label)
+            .unwrap_or_else(|| panic!("Unknown charset: {}", label));
+        self
+    }
+
+    /// Reject requests with unexpected content type with
+    /// *415 Unsupported Media Type* response.
+    ///
+    /// By default content type error is passed to the error handler.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set predicate for additionally accepted content types.
+    ///
+    /// `application/x-www-form-urlencoded` is always accepted.
+    pub fn content_type<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&Mime) -> bool + 'static,
+    {
+        self.ctype = Some(Rc::new(f));
+        self
+    }
+
+    fn accepts(&self, mime: &Mime) -> bool {
+        is_form(mime) || self.ctype.as_ref().map_or(false, |f| f(mime))
+    }
+
     /// Set custom error handler
     pub fn error_handler<F>(&mut self, f: F) -> &mut Self
     where
@@ -381,6 +653,9 @@ impl Default for FormConfig {
     fn default() -> Self {
         FormConfig {
             limit: 262_144,
+            encoding: UTF_8,
+            strict: false,
+            ctype: None,
             ehandler: Rc::new(|e, _| e.into()),
         }
     }
@@ -511,16 +786,29 @@ where
 
     #[inline]
     fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
-        let cfg = JsonConfig::default();
+        let cfg = load_config::<JsonConfig>(req);
+
+        // check content-type
+        if !content_type_matches(req, |mime| cfg.accepts(mime)) {
+            return Box::new(err(if cfg.strict {
+                unsupported_media_type(req)
+            } else {
+                (*cfg.ehandler)(JsonPayloadError::ContentType, req)
+            }));
+        }
 
         let req2 = req.clone();
-        let err = Rc::clone(&cfg.ehandler);
-        Box::new(
-            JsonBody::new(req)
-                .limit(cfg.limit)
-                .map_err(move |e| (*err)(e, &req2))
-                .map(Json),
-        )
+        let ehandler = Rc::clone(&cfg.ehandler);
+        let fut: Box<Future<Item = T, Error = JsonPayloadError>> =
+            if content_type_matches(req, |mime| {
+                mime.type_() == mime::APPLICATION && mime.subtype() == mime::JSON
+            }) {
+                Box::new(JsonBody::new(req).limit(cfg.limit))
+            } else {
+                let mut req = Retyped::new(req, "application/json");
+                Box::new(JsonBody::new(&mut req).limit(cfg.limit))
+            };
+        Box::new(fut.map_err(move |e| (*ehandler)(e, &req2)).map(Json))
     }
 }
 
@@ -554,8 +842,11 @@ where
 ///     });
 /// }
 /// ```
+#[derive(Clone)]
 pub struct JsonConfig {
     limit: usize,
+    strict: bool,
+    ctype: Option<Rc<Fn(&Mime) -> bool>>,
     ehandler: Rc<Fn(JsonPayloadError, &HttpRequest) -> Error>,
 }
 
@@ -566,6 +857,43 @@ impl JsonConfig {
         self
     }
 
+    /// Reject requests with unexpected content type with
+    /// *415 Unsupported Media Type* response.
+    ///
+    /// By default content type error is passed to the error handler.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set predicate for additionally accepted content types.
+    ///
+    /// `application/json` and `application/*+json` (i.e.
+    /// `application/vnd.api+json`) are always accepted.
+    ///
+    /// ```rust
+    /// use actix_web2::{dev, App};
+    ///
+    /// fn main() {
+    ///     let app = App::new().config(|cfg: &mut dev::JsonConfig| {
+    ///         cfg.content_type(|mime| mime.subtype() == "x-json");
+    ///     });
+    /// }
+    /// ```
+    pub fn content_type<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&Mime) -> bool + 'static,
+    {
+        self.ctype = Some(Rc::new(f));
+        self
+    }
+
+    fn accepts(&self, mime: &Mime) -> bool {
+        (mime.type_() == mime::APPLICATION
+            && (mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON)))
+            || self.ctype.as_ref().map_or(false, |f| f(mime))
+    }
+
     /// Set custom error handler
     pub fn error_handler<F>(&mut self, f: F) -> &mut Self
     where
@@ -580,11 +908,230 @@ impl Default for JsonConfig {
     fn default() -> Self {
         JsonConfig {
             limit: 262_144,
+            strict: false,
+            ctype: None,
             ehandler: Rc::new(|e, _| e.into()),
         }
     }
 }
 
+/// Content-type negotiated request body extractor.
+///
+/// Decoder is selected by the request's `Content-Type` header. *JSON* and
+/// *urlencoded form* payloads are supported out of the box, they are decoded
+/// with [**JsonConfig**](dev/struct.JsonConfig.html) and
+/// [**FormConfig**](dev/struct.FormConfig.html) settings. Additional formats
+/// could be registered with [**AnyBodyConfig**](dev/struct.AnyBodyConfig.html).
+/// Request with any other content type is rejected with
+/// *415 Unsupported Media Type* response.
+///
+/// ## Example
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{AnyBody, App};
+///
+/// #[derive(Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// accepts both `application/json` and `application/x-www-form-urlencoded`
+/// fn index(info: AnyBody<Info>) -> String {
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/index.html", |r| r.post(index));
+/// }
+/// ```
+pub struct AnyBody<T>(pub T);
+
+impl<T> AnyBody<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for AnyBody<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for AnyBody<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for AnyBody<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for AnyBody<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T, P> FromRequest<P> for AnyBody<T>
+where
+    T: DeserializeOwned + 'static,
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Error = Error;
+    type Future = Box<Future<Item = Self, Error = Error>>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        let mime = match req.mime_type() {
+            Ok(Some(mime)) => mime,
+            Ok(None) => {
+                return Box::new(err(ErrorUnsupportedMediaType(
+                    "Content-Type header is expected",
+                )));
+            }
+            Err(e) => return Box::new(err(e.into())),
+        };
+
+        let cfg = load_config::<AnyBodyConfig>(req);
+
+        // registered formats take precedence over built-in ones
+        if let Some(decoder) = cfg.decoder(&mime) {
+            return Box::new(
+                MessageBody::new(req)
+                    .limit(cfg.limit)
+                    .from_err()
+                    .and_then(move |body| decode_format::<T>(&decoder, &body))
+                    .map(AnyBody),
+            );
+        }
+
+        if load_config::<JsonConfig>(req).accepts(&mime) {
+            Box::new(Json::<T>::from_request(req).map(|item| AnyBody(item.into_inner())))
+        } else if load_config::<FormConfig>(req).accepts(&mime) {
+            Box::new(Form::<T>::from_request(req).map(|item| AnyBody(item.into_inner())))
+        } else {
+            Box::new(err(ErrorUnsupportedMediaType(format!(
+                "Unsupported Content-Type: {}",
+                mime
+            ))))
+        }
+    }
+}
+
+type FormatDecoder = Rc<Fn(&[u8], FormatDeserializer) -> Result<(), Error>>;
+
+/// Deserialize payload to `T` with the registered format decoder
+fn decode_format<T: DeserializeOwned>(
+    decoder: &FormatDecoder,
+    body: &[u8],
+) -> Result<T, Error> {
+    let mut value = None;
+    (**decoder)(
+        body,
+        FormatDeserializer {
+            target: &mut |de| {
+                value = Some(erased_serde::deserialize::<T>(de)?);
+                Ok(())
+            },
+        },
+    )?;
+    value.ok_or_else(|| ErrorBadRequest("Payload is not deserialized"))
+}
+
+/// Deserializer handle passed to the format decoders registered with
+/// [**AnyBodyConfig**](struct.AnyBodyConfig.html).
+///
+/// Requested type is deserialized directly from the format's deserializer.
+pub struct FormatDeserializer<'a> {
+    target: &'a mut FnMut(
+        &mut erased_serde::Deserializer,
+    ) -> Result<(), erased_serde::Error>,
+}
+
+impl<'a> FormatDeserializer<'a> {
+    /// Deserialize requested type with the format's deserializer.
+    ///
+    /// Deserialization error is returned as *400 Bad Request* error.
+    pub fn deserialize<'de, D>(self, deserializer: D) -> Result<(), Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut de = erased_serde::Deserializer::erase(deserializer);
+        (self.target)(&mut de).map_err(ErrorBadRequest)
+    }
+}
+
+/// AnyBody extractor configuration
+///
+/// Registers additional payload formats for [**AnyBody**](../struct.AnyBody.html)
+/// extractor. Format decoder passes payload to the format's deserializer,
+/// requested type is deserialized directly from it.
+///
+/// ```rust
+/// use actix_web2::{dev, App};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::AnyBodyConfig| {
+///         cfg.limit(4096)
+///             .format("application/x-amz-json-1.1", |body, de| {
+///                 de.deserialize(&mut serde_json::Deserializer::from_slice(body))
+///             });
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct AnyBodyConfig {
+    limit: usize,
+    formats: Vec<(String, FormatDecoder)>,
+}
+
+impl AnyBodyConfig {
+    /// Change max size of payload for registered formats.
+    /// By default max size is 256Kb
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Register decoder for a content type, i.e. `application/x-yaml`.
+    ///
+    /// Content type parameters are ignored during matching.
+    pub fn format<F>(&mut self, content_type: &str, f: F) -> &mut Self
+    where
+        F: Fn(&[u8], FormatDeserializer) -> Result<(), Error> + 'static,
+    {
+        let content_type = content_type.to_lowercase();
+        self.formats.retain(|(ct, _)| *ct != content_type);
+        self.formats.push((content_type, Rc::new(f)));
+        self
+    }
+
+    fn decoder(&self, mime: &Mime) -> Option<FormatDecoder> {
+        let essence = format!("{}/{}", mime.type_(), mime.subtype()).to_lowercase();
+        self.formats
+            .iter()
+            .find(|(ct, _)| *ct == essence)
+            .map(|(_, decoder)| decoder.clone())
+    }
+}
+
+impl Default for AnyBodyConfig {
+    fn default() -> Self {
+        AnyBodyConfig {
+            limit: 262_144,
+            formats: Vec::new(),
+        }
+    }
+}
+
 /// Request payload extractor.
 ///
 /// Loads request's payload and construct Bytes instance.
@@ -619,7 +1166,7 @@ where
 
     #[inline]
     fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
-        let cfg = PayloadConfig::default();
+        let cfg = load_config::<PayloadConfig>(req);
 
         if let Err(e) = cfg.check_mimetype(req) {
             return Either::B(err(e));
@@ -666,7 +1213,7 @@ where
 
     #[inline]
     fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
-        let cfg = PayloadConfig::default();
+        let cfg = load_config::<PayloadConfig>(req);
 
         // check content-type
         if let Err(e) = cfg.check_mimetype(req) {
@@ -825,10 +1372,249 @@ where
     }
 }
 
+/// Extract either `A` or `B` from the request
+///
+/// `A` extractor is tried first, `B` is used if `A` fails. If both extractors
+/// fail, [**EitherExtractError**](dev/struct.EitherExtractError.html) with both
+/// errors is returned. Request's payload is passed to `A` extractor as it
+/// reads it and is kept, so `B` extractor can read it again. Payload is not
+/// read if `A` does not need it, i.e. for `Path` or `Query` extractors.
+/// Max size of the kept payload is configured with
+/// [**PayloadConfig**](dev/struct.PayloadConfig.html).
+///
+/// ## Example
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{App, Either, Form, Json};
+///
+/// #[derive(Deserialize)]
+/// struct Event {
+///     kind: String,
+/// }
+///
+/// /// accept json or urlencoded payload
+/// fn webhook(event: Either<Json<Event>, Form<Event>>) -> String {
+///     match event {
+///         Either::A(json) => format!("Json event: {}", json.kind),
+///         Either::B(form) => format!("Form event: {}", form.kind),
+///     }
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/webhook", |r| r.post(webhook));
+/// }
+/// ```
+impl<A, B, P> FromRequest<P> for responder::Either<A, B>
+where
+    A: FromRequest<P> + 'static,
+    A::Future: 'static,
+    B: FromRequest<P> + 'static,
+    B::Future: 'static,
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Error = Error;
+    type Future = EitherExtractFut<A, B, P>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        let cfg = load_config::<PayloadConfig>(req);
+        let payload = req.take_payload();
+        let (feed, replayed) = replay_channel();
+        let mut sreq = ServiceRequest::from_parts(req.request().clone(), replayed);
+
+        EitherExtractFut {
+            req: req.request().clone(),
+            state: EitherExtractState::A(A::from_request(&mut sreq), feed),
+            replay: Replay {
+                payload,
+                eof: false,
+                chunks: Vec::new(),
+                size: 0,
+                limit: cfg.limit,
+            },
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct EitherExtractFut<A, B, P>
+where
+    A: FromRequest<P>,
+    B: FromRequest<P>,
+{
+    req: HttpRequest,
+    state: EitherExtractState<A::Future, B::Future>,
+    replay: Replay<P>,
+}
+
+enum EitherExtractState<FA, FB> {
+    A(FA, Feed),
+    B(Option<Error>, FB, Feed),
+}
+
+impl<A, B, P> Future for EitherExtractFut<A, B, P>
+where
+    A: FromRequest<P>,
+    B: FromRequest<P>,
+    P: Stream<Item = Bytes, Error = PayloadError>,
+{
+    type Item = responder::Either<A, B>;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let state = match self.state {
+                EitherExtractState::A(ref mut fut, ref mut feed) => match fut.poll() {
+                    Ok(Async::Ready(a)) => {
+                        return Ok(Async::Ready(responder::Either::A(a)));
+                    }
+                    Ok(Async::NotReady) => {
+                        if self.replay.forward(feed, true)? {
+                            continue;
+                        }
+                        return Ok(Async::NotReady);
+                    }
+                    Err(e) => {
+                        // replay payload read by `A` extractor
+                        let (mut feed, replayed) = replay_channel();
+                        for chunk in self.replay.chunks.drain(..) {
+                            feed(Some(chunk));
+                        }
+                        if self.replay.eof {
+                            feed(None);
+                        }
+                        let mut sreq =
+                            ServiceRequest::from_parts(self.req.clone(), replayed);
+                        EitherExtractState::B(
+                            Some(e.into()),
+                            B::from_request(&mut sreq),
+                            feed,
+                        )
+                    }
+                },
+                EitherExtractState::B(ref mut a_err, ref mut fut, ref mut feed) => {
+                    match fut.poll() {
+                        Ok(Async::Ready(b)) => {
+                            return Ok(Async::Ready(responder::Either::B(b)));
+                        }
+                        Ok(Async::NotReady) => {
+                            if self.replay.forward(feed, false)? {
+                                continue;
+                            }
+                            return Ok(Async::NotReady);
+                        }
+                        Err(e) => {
+                            return Err(EitherExtractError {
+                                a: a_err.take().expect("Future is completed"),
+                                b: e.into(),
+                            }
+                            .into());
+                        }
+                    }
+                }
+            };
+            self.state = state;
+        }
+    }
+}
+
+/// Sends chunks to the replayed payload, `None` is the end of payload
+type Feed = Box<FnMut(Option<Bytes>)>;
+
+fn replay_channel<P>() -> (Feed, Payload<P>) {
+    let (mut sender, payload) = h1::Payload::create(false);
+    let feed = move |chunk: Option<Bytes>| match chunk {
+        Some(chunk) => sender.feed_data(chunk),
+        None => sender.feed_eof(),
+    };
+    (Box::new(feed), Payload::H1(payload))
+}
+
+/// Request's payload, read on demand of the extractor
+struct Replay<P> {
+    payload: Payload<P>,
+    eof: bool,
+    chunks: Vec<Bytes>,
+    size: usize,
+    limit: usize,
+}
+
+impl<P> Replay<P>
+where
+    P: Stream<Item = Bytes, Error = PayloadError>,
+{
+    /// Pass next chunk of the request's payload to the extractor, chunk is
+    /// kept for the replay if `keep` is set. Returns `false` if there is no
+    /// chunk available.
+    fn forward(&mut self, feed: &mut Feed, keep: bool) -> Result<bool, Error> {
+        if self.eof {
+            return Ok(false);
+        }
+        match self.payload.poll()? {
+            Async::Ready(Some(chunk)) => {
+                if keep {
+                    self.size += chunk.len();
+                    if self.size > self.limit {
+                        return Err(PayloadError::Overflow.into());
+                    }
+                    self.chunks.push(chunk.clone());
+                }
+                feed(Some(chunk));
+                Ok(true)
+            }
+            Async::Ready(None) => {
+                self.eof = true;
+                feed(None);
+                Ok(true)
+            }
+            Async::NotReady => Ok(false),
+        }
+    }
+}
+
+/// Create payload from the buffered body
+pub(crate) fn buffered_payload<P>(body: &Bytes) -> Payload<P> {
+    if body.is_empty() {
+        Payload::None
+    } else {
+        let (mut sender, payload) = h1::Payload::create(false);
+        sender.feed_data(body.clone());
+        sender.feed_eof();
+        Payload::H1(payload)
+    }
+}
+
+/// Errors of both `Either` extractor alternatives
+///
+/// Generates *400 Bad Request* response.
+#[derive(Debug)]
+pub struct EitherExtractError {
+    /// Error of the first extractor
+    pub a: Error,
+    /// Error of the second extractor
+    pub b: Error,
+}
+
+impl fmt::Display for EitherExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Both extractors failed: {}; {}", self.a, self.b)
+    }
+}
+
+impl ResponseError for EitherExtractError {
+    fn error_response(&self) -> Response {
+        Response::build(StatusCode::BAD_REQUEST).body(self.to_string())
+    }
+}
+
 /// Payload configuration for request's payload.
+#[derive(Clone)]
 pub struct PayloadConfig {
     limit: usize,
     mimetype: Option<Mime>,
+    strict: bool,
+    ctype: Option<Rc<Fn(&Mime) -> bool>>,
 }
 
 impl PayloadConfig {
@@ -840,29 +1626,71 @@ impl PayloadConfig {
 
     /// Set required mime-type of the request. By default mime type is not
     /// enforced.
+    ///
+    /// Mime type parameters are ignored, structured syntax suffix variants
+    /// are accepted as well, i.e. `application/vnd.api+json` matches
+    /// `application/json`.
     pub fn mimetype(&mut self, mt: Mime) -> &mut Self {
         self.mimetype = Some(mt);
         self
     }
 
+    /// Reject requests with unexpected content type with
+    /// *415 Unsupported Media Type* response. By default
+    /// *400 Bad Request* response is generated.
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set predicate for accepted content types, it is applied
+    /// in addition to the required mime-type.
+    pub fn content_type<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(&Mime) -> bool + 'static,
+    {
+        self.ctype = Some(Rc::new(f));
+        self
+    }
+
+    fn accepts(&self, mime: &Mime) -> bool {
+        if let Some(ref mt) = self.mimetype {
+            if mt.type_() == mime.type_()
+                && (mt.subtype() == mime.subtype()
+                    || mime.suffix() == Some(mt.subtype()))
+            {
+                return true;
+            }
+        }
+        self.ctype.as_ref().map_or(false, |f| f(mime))
+    }
+
     fn check_mimetype<P>(&self, req: &ServiceRequest<P>) -> Result<(), Error> {
+        if self.mimetype.is_none() && self.ctype.is_none() {
+            return Ok(());
+        }
+
         // check content-type
-        if let Some(ref mt) = self.mimetype {
-            match req.mime_type() {
-                Ok(Some(ref req_mt)) => {
-                    if mt != req_mt {
-                        return Err(ErrorBadRequest("Unexpected Content-Type"));
-                    }
-                }
-                Ok(None) => {
+        let accepted = match req.mime_type() {
+            Ok(Some(ref mime)) => self.accepts(mime),
+            Ok(None) => {
+                if !self.strict {
                     return Err(ErrorBadRequest("Content-Type is expected"));
                 }
-                Err(err) => {
-                    return Err(err.into());
-                }
+                false
             }
+            Err(err) => {
+                return Err(err.into());
+            }
+        };
+
+        if accepted {
+            Ok(())
+        } else if self.strict {
+            Err(unsupported_media_type(req))
+        } else {
+            Err(ErrorBadRequest("Unexpected Content-Type"))
         }
-        Ok(())
     }
 }
 
@@ -871,10 +1699,74 @@ impl Default for PayloadConfig {
         PayloadConfig {
             limit: 262_144,
             mimetype: None,
+            strict: false,
+            ctype: None,
+        }
+    }
+}
+
+/// Check request's content type with the predicate, request without
+/// content type does not match.
+pub(crate) fn content_type_matches<F>(req: &HttpRequest, f: F) -> bool
+where
+    F: Fn(&Mime) -> bool,
+{
+    match req.mime_type() {
+        Ok(Some(ref mime)) => f(mime),
+        _ => false,
+    }
+}
+
+fn is_form(mime: &Mime) -> bool {
+    mime.type_() == mime::APPLICATION && mime.subtype() == mime::WWW_FORM_URLENCODED
+}
+
+/// Request message with replaced `Content-Type` header.
+///
+/// `JsonBody` and `UrlEncoded` futures accept canonical content type only,
+/// payloads accepted by the extractor configuration are passed to them
+/// as a retyped message.
+struct Retyped<P> {
+    headers: HeaderMap,
+    payload: Payload<P>,
+}
+
+impl<P> Retyped<P> {
+    fn new(req: &mut ServiceRequest<P>, content_type: &'static str) -> Self {
+        let mut headers = req.headers().clone();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        Retyped {
+            headers,
+            payload: req.take_payload(),
         }
     }
 }
 
+impl<P> HttpMessage for Retyped<P> {
+    type Stream = P;
+
+    fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    fn take_payload(&mut self) -> Payload<P> {
+        std::mem::replace(&mut self.payload, Payload::None)
+    }
+}
+
+fn unsupported_media_type(req: &HttpRequest) -> Error {
+    ErrorUnsupportedMediaType(format!(
+        "Unsupported Content-Type: {}",
+        req.content_type()
+    ))
+}
+
+/// Load extractor configuration registered with `App::config()`,
+/// default configuration is used if it is not registered.
+pub(crate) fn load_config<C: Clone + Default + 'static>(req: &HttpRequest) -> C {
+    req.app_extensions().get::<C>().cloned().unwrap_or_default()
+}
+
 macro_rules! tuple_from_req ({$fut_type:ident, $(($n:tt, $T:ident)),+} => {
 
     /// FromRequest implementation for tuple
@@ -1000,50 +1892,62 @@ tuple_from_req!(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::*;
     use actix_http::http::header;
     use actix_http::test::TestRequest;
+    use actix_http::{Extensions, PayloadStream, Request};
+    use actix_router::{ResourceDef, Url};
+    use actix_service::{IntoNewService, NewService, Service};
     use bytes::Bytes;
-    use futures::{Async, Future};
+    use futures::Future;
     use mime;
-    use serde::{Deserialize, Serialize};
+    use serde_derive::Deserialize;
+    use std::collections::HashMap;
 
-    use crate::resource::Resource;
-    // use crate::router::{ResourceDef, Router};
+    use crate::service::ServiceResponse;
+    use crate::App;
 
     #[derive(Deserialize, Debug, PartialEq)]
     struct Info {
         hello: String,
     }
 
+    /// Service request with path matched against `pattern`, `cfg` is
+    /// registered as application config
+    fn service_request<C: 'static>(
+        req: Request,
+        pattern: &str,
+        cfg: C,
+    ) -> ServiceRequest<PayloadStream> {
+        let mut path = actix_router::Path::new(Url::new(req.uri().clone()));
+        assert!(ResourceDef::new(pattern).match_path(&mut path));
+        let mut extensions = Extensions::new();
+        extensions.insert(cfg);
+        ServiceRequest::new(path, req, Rc::new(extensions))
+    }
+
     #[test]
     fn test_bytes() {
-        let cfg = PayloadConfig::default();
         let req = TestRequest::with_header(header::CONTENT_LENGTH, "11")
             .set_payload(Bytes::from_static(b"hello=world"))
             .finish();
+        let mut req = service_request(req, "/", ());
 
-        match Bytes::from_request(&req, &cfg).unwrap().poll().unwrap() {
-            Async::Ready(s) => {
-                assert_eq!(s, Bytes::from_static(b"hello=world"));
-            }
-            _ => unreachable!(),
-        }
+        let s = Bytes::from_request(&mut req).wait().unwrap();
+        assert_eq!(s, Bytes::from_static(b"hello=world"));
     }
 
     #[test]
     fn test_string() {
-        let cfg = PayloadConfig::default();
         let req = TestRequest::with_header(header::CONTENT_LENGTH, "11")
             .set_payload(Bytes::from_static(b"hello=world"))
             .finish();
+        let mut req = service_request(req, "/", ());
 
-        match String::from_request(&req, &cfg).unwrap().poll().unwrap() {
-            Async::Ready(s) => {
-                assert_eq!(s, "hello=world");
-            }
-            _ => unreachable!(),
-        }
+        let s = String::from_request(&mut req).wait().unwrap();
+        assert_eq!(s, "hello=world");
     }
 
     #[test]
@@ -1058,128 +1962,399 @@ mod tests {
 
         let mut cfg = FormConfig::default();
         cfg.limit(4096);
-        match Form::<Info>::from_request(&req, &cfg).poll().unwrap() {
-            Async::Ready(s) => {
-                assert_eq!(s.hello, "world");
-            }
-            _ => unreachable!(),
+        let mut req = service_request(req, "/", cfg);
+
+        let s = Form::<Info>::from_request(&mut req).wait().unwrap();
+        assert_eq!(s.hello, "world");
+    }
+
+    #[test]
+    fn test_decode_form() {
+        use encoding::all::{ISO_8859_1, WINDOWS_1251};
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Info {
+            name: String,
+            city: String,
+        }
+
+        let info: Info = decode_form(b"name=J%F6rg+M&city=K%F6ln", ISO_8859_1).unwrap();
+        assert_eq!(info.name, "J\u{f6}rg M");
+        assert_eq!(info.city, "K\u{f6}ln");
+
+        let info: Info =
+            decode_form(b"name=%C8%E2%E0%ED&city=%CC%EE%F1%EA%E2%E0", WINDOWS_1251)
+                .unwrap();
+        assert_eq!(info.name, "\u{418}\u{432}\u{430}\u{43d}");
+        assert_eq!(info.city, "\u{41c}\u{43e}\u{441}\u{43a}\u{432}\u{430}");
+
+        let info: Info = decode_form(b"name=J%C3%B6rg&city=", UTF_8).unwrap();
+        assert_eq!(info.name, "J\u{f6}rg");
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Order {
+            item: String,
+            count: u32,
         }
+
+        // escaped delimiters are kept, values are parsed to the field type
+        let order: Order = decode_form(b"item=A%26B%3D%2B&count=3", ISO_8859_1).unwrap();
+        assert_eq!(
+            order,
+            Order {
+                item: "A&B=+".to_owned(),
+                count: 3
+            }
+        );
     }
 
     #[test]
     fn test_option() {
+        let form = |body: &'static [u8]| {
+            let req = TestRequest::with_header(
+                header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .set_payload(Bytes::from_static(body))
+            .finish();
+            let mut req = service_request(req, "/", ());
+            Option::<Form<Info>>::from_request(&mut req).wait().unwrap()
+        };
+
+        assert_eq!(form(b""), None);
+        assert_eq!(
+            form(b"hello=world"),
+            Some(Form(Info {
+                hello: "world".into()
+            }))
+        );
+        assert_eq!(form(b"bye=world"), None);
+    }
+
+    #[test]
+    fn test_result() {
+        let form = |body: &'static [u8]| {
+            let req = TestRequest::with_header(
+                header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .set_payload(Bytes::from_static(body))
+            .finish();
+            let mut req = service_request(req, "/", ());
+            Result::<Form<Info>, Error>::from_request(&mut req)
+                .wait()
+                .unwrap()
+        };
+
+        assert_eq!(
+            form(b"hello=world").unwrap(),
+            Form(Info {
+                hello: "world".into()
+            })
+        );
+        assert!(form(b"bye=world").is_err());
+    }
+
+    #[test]
+    fn test_payload_config() {
+        let mut cfg = PayloadConfig::default();
+        cfg.mimetype(mime::APPLICATION_JSON);
+
+        let req = service_request(TestRequest::default().finish(), "/", ());
+        assert!(cfg.check_mimetype(&req).is_err());
+
         let req = TestRequest::with_header(
             header::CONTENT_TYPE,
             "application/x-www-form-urlencoded",
         )
         .finish();
+        let req = service_request(req, "/", ());
+        assert!(cfg.check_mimetype(&req).is_err());
+
+        let req =
+            TestRequest::with_header(header::CONTENT_TYPE, "application/json").finish();
+        let req = service_request(req, "/", ());
+        assert!(cfg.check_mimetype(&req).is_ok());
+    }
+
+    #[test]
+    fn test_json_content_type() {
+        let mut cfg = JsonConfig::default();
+        assert!(cfg.accepts(&mime::APPLICATION_JSON));
+        assert!(cfg.accepts(&"application/vnd.api+json".parse().unwrap()));
+        assert!(!cfg.accepts(&mime::TEXT_PLAIN));
+
+        cfg.content_type(|mime| *mime == mime::TEXT_PLAIN);
+        assert!(cfg.accepts(&mime::TEXT_PLAIN));
+        assert!(!cfg.accepts(&mime::APPLICATION_WWW_FORM_URLENCODED));
+    }
+
+    fn extract<T, C>(content_type: &str, body: &'static [u8], cfg: C) -> Result<T, Error>
+    where
+        T: FromRequest<PayloadStream, Error = Error>,
+        C: 'static,
+    {
+        let req = TestRequest::with_header(header::CONTENT_TYPE, content_type)
+            .set_payload(Bytes::from_static(body))
+            .finish();
+        T::from_request(&mut service_request(req, "/", cfg)).wait()
+    }
+
+    fn status(e: Error) -> StatusCode {
+        e.as_response_error().error_response().status()
+    }
+
+    const JSON: &[u8] = b"{\"hello\": \"world\"}";
+
+    #[test]
+    fn test_json_strict() {
+        let mut cfg = JsonConfig::default();
+        let e = extract::<Json<Info>, _>("text/plain", JSON, cfg.clone()).unwrap_err();
+        assert_eq!(status(e), StatusCode::BAD_REQUEST);
+
+        cfg.strict(true);
+        let e = extract::<Json<Info>, _>("text/plain", JSON, cfg.clone()).unwrap_err();
+        assert_eq!(status(e), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let json = extract::<Json<Info>, _>("application/vnd.api+json", JSON, cfg);
+        assert_eq!(json.unwrap().hello, "world");
+    }
+
+    #[test]
+    fn test_json_content_type_predicate() {
+        let mut cfg = JsonConfig::default();
+        cfg.strict(true)
+            .content_type(|mime| mime.subtype() == "x-json");
 
+        let json =
+            extract::<Json<Info>, _>("text/x-json; charset=utf-8", JSON, cfg.clone());
+        assert_eq!(json.unwrap().hello, "world");
+
+        let e = extract::<Json<Info>, _>("text/plain", JSON, cfg).unwrap_err();
+        assert_eq!(status(e), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_form_strict() {
         let mut cfg = FormConfig::default();
-        cfg.limit(4096);
+        let e = extract::<Form<Info>, _>("text/plain", b"hello=world", cfg.clone())
+            .unwrap_err();
+        assert_eq!(status(e), StatusCode::BAD_REQUEST);
 
-        match Option::<Form<Info>>::from_request(&req, &cfg)
-            .poll()
-            .unwrap()
-        {
-            Async::Ready(r) => assert_eq!(r, None),
-            _ => unreachable!(),
-        }
+        cfg.strict(true);
+        let e = extract::<Form<Info>, _>("text/plain", b"hello=world", cfg).unwrap_err();
+        assert_eq!(status(e), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
 
-        let req = TestRequest::with_header(
-            header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded",
-        )
-        .header(header::CONTENT_LENGTH, "9")
-        .set_payload(Bytes::from_static(b"hello=world"))
-        .finish();
+    #[test]
+    fn test_form_content_type_predicate() {
+        let mut cfg = FormConfig::default();
+        cfg.strict(true)
+            .content_type(|mime| *mime == mime::TEXT_PLAIN);
 
-        match Option::<Form<Info>>::from_request(&req, &cfg)
-            .poll()
-            .unwrap()
-        {
-            Async::Ready(r) => assert_eq!(
-                r,
-                Some(Form(Info {
-                    hello: "world".into()
-                }))
-            ),
-            _ => unreachable!(),
-        }
+        let form = extract::<Form<Info>, _>("text/plain", b"hello=world", cfg.clone());
+        assert_eq!(form.unwrap().hello, "world");
+
+        let e = extract::<Form<Info>, _>("text/html", b"hello=world", cfg).unwrap_err();
+        assert_eq!(status(e), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_payload_config_strict() {
+        let mut cfg = PayloadConfig::default();
+        cfg.mimetype(mime::APPLICATION_JSON).strict(true);
+
+        let req = TestRequest::with_header(header::CONTENT_TYPE, "text/plain").finish();
+        let req = service_request(req, "/", ());
+        let e = cfg.check_mimetype(&req).unwrap_err();
+        assert_eq!(
+            e.as_response_error().error_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
 
         let req = TestRequest::with_header(
             header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded",
+            "application/vnd.api+json; charset=utf-8",
         )
-        .header(header::CONTENT_LENGTH, "9")
-        .set_payload(Bytes::from_static(b"bye=world"))
         .finish();
+        let req = service_request(req, "/", ());
+        assert!(cfg.check_mimetype(&req).is_ok());
+    }
+
+    #[test]
+    fn test_either_error() {
+        let e = EitherExtractError {
+            a: ErrorBadRequest("a failed"),
+            b: ErrorNotFound("b failed"),
+        };
+        assert_eq!(
+            format!("{}", e),
+            "Both extractors failed: a failed; b failed"
+        );
+        assert_eq!(e.error_response().status(), StatusCode::BAD_REQUEST);
+    }
 
-        match Option::<Form<Info>>::from_request(&req, &cfg)
-            .poll()
+    fn either_service(
+    ) -> impl Service<Request = Request, Response = ServiceResponse, Error = ()> {
+        App::new()
+            .config(|cfg: &mut PayloadConfig| {
+                cfg.limit(64);
+            })
+            .resource("/either", |r| {
+                r.to(|e: responder::Either<Json<Info>, Form<Info>>| match e {
+                    responder::Either::A(json) => format!("json: {}", json.hello),
+                    responder::Either::B(form) => format!("form: {}", form.hello),
+                })
+            })
+            .resource("/either/{hello}", |r| {
+                r.to(|e: responder::Either<Path<u32>, Query<Info>>| match e {
+                    responder::Either::A(path) => format!("path: {}", path),
+                    responder::Either::B(query) => format!("query: {}", query.hello),
+                })
+            })
+            .into_new_service()
+            .new_service()
+            .wait()
             .unwrap()
-        {
-            Async::Ready(r) => assert_eq!(r, None),
-            _ => unreachable!(),
-        }
+    }
+
+    fn call_either(req: Request) -> (StatusCode, Bytes) {
+        use actix_http::body::{Body, ResponseBody};
+
+        let res = either_service().call(req).wait().unwrap();
+        let body = match res.response().body() {
+            ResponseBody::Body(Body::Bytes(b)) | ResponseBody::Other(Body::Bytes(b)) => {
+                b.clone()
+            }
+            _ => Bytes::new(),
+        };
+        (res.status(), body)
     }
 
     #[test]
-    fn test_result() {
-        let req = TestRequest::with_header(
-            header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded",
-        )
-        .header(header::CONTENT_LENGTH, "11")
-        .set_payload(Bytes::from_static(b"hello=world"))
-        .finish();
+    fn test_either_fallback() {
+        let req = TestRequest::with_uri("/either")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(b"{\"hello\": \"world\"}"))
+            .finish();
+        assert_eq!(
+            call_either(req),
+            (StatusCode::OK, Bytes::from_static(b"json: world"))
+        );
+
+        // `Json` extractor reads the payload and fails, `Form` extractor
+        // reads it again
+        let req = TestRequest::with_uri("/either")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .set_payload(Bytes::from_static(b"hello=world"))
+            .finish();
+        assert_eq!(
+            call_either(req),
+            (StatusCode::OK, Bytes::from_static(b"form: world"))
+        );
+
+        let req = TestRequest::with_uri("/either")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(b"{\"hello\": 1}"))
+            .finish();
+        // both errors are reported
+        let (status, body) = call_either(req);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body = str::from_utf8(&body).unwrap();
+        assert!(body.starts_with("Both extractors failed: Json deserialize error"));
+        assert!(body.ends_with("; Content type error"));
+
+        // kept payload is limited
+        let req = TestRequest::with_uri("/either")
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from(vec![b' '; 128]))
+            .finish();
+        assert_eq!(call_either(req).0, StatusCode::PAYLOAD_TOO_LARGE);
+    }
 
-        match Result::<Form<Info>, Error>::from_request(&req, &FormConfig::default())
-            .poll()
-            .unwrap()
-        {
-            Async::Ready(Ok(r)) => assert_eq!(
-                r,
-                Form(Info {
-                    hello: "world".into()
-                })
-            ),
-            _ => unreachable!(),
-        }
+    #[test]
+    fn test_either_without_payload() {
+        // payload is not read by `Path` and `Query` extractors
+        let req = TestRequest::with_uri("/either/32")
+            .set_payload(Bytes::from(vec![b'x'; 1024]))
+            .finish();
+        assert_eq!(
+            call_either(req),
+            (StatusCode::OK, Bytes::from_static(b"path: 32"))
+        );
 
-        let req = TestRequest::with_header(
-            header::CONTENT_TYPE,
-            "application/x-www-form-urlencoded",
-        )
-        .header(header::CONTENT_LENGTH, "9")
-        .set_payload(Bytes::from_static(b"bye=world"))
-        .finish();
+        let req = TestRequest::with_uri("/either/name?hello=world")
+            .set_payload(Bytes::from(vec![b'x'; 1024]))
+            .finish();
+        assert_eq!(
+            call_either(req),
+            (StatusCode::OK, Bytes::from_static(b"query: world"))
+        );
+    }
 
-        match Result::<Form<Info>, Error>::from_request(&req, &FormConfig::default())
-            .poll()
-            .unwrap()
-        {
-            Async::Ready(r) => assert!(r.is_err()),
-            _ => unreachable!(),
-        }
+    #[test]
+    fn test_any_body_config() {
+        let mut cfg = AnyBodyConfig::default();
+        cfg.format("Application/X-Yaml", |_, _| Ok(()));
+
+        let mime: Mime = "application/x-yaml; charset=utf-8".parse().unwrap();
+        assert!(cfg.decoder(&mime).is_some());
+        assert!(cfg.decoder(&mime::APPLICATION_JSON).is_none());
     }
 
     #[test]
-    fn test_payload_config() {
-        let req = TestRequest::default().finish();
-        let mut cfg = PayloadConfig::default();
-        cfg.mimetype(mime::APPLICATION_JSON);
-        assert!(cfg.check_mimetype(&req).is_err());
+    fn test_any_body() {
+        let body = extract::<AnyBody<Info>, _>("application/json", JSON, ()).unwrap();
+        assert_eq!(body.hello, "world");
 
-        let req = TestRequest::with_header(
-            header::CONTENT_TYPE,
+        let body = extract::<AnyBody<Info>, _>(
             "application/x-www-form-urlencoded",
+            b"hello=world",
+            (),
         )
-        .finish();
-        assert!(cfg.check_mimetype(&req).is_err());
+        .unwrap();
+        assert_eq!(body.hello, "world");
 
-        let req =
-            TestRequest::with_header(header::CONTENT_TYPE, "application/json").finish();
-        assert!(cfg.check_mimetype(&req).is_ok());
+        let e = extract::<AnyBody<Info>, _>("text/plain", JSON, ()).unwrap_err();
+        assert_eq!(status(e), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        // content type is required
+        let req = TestRequest::default()
+            .set_payload(Bytes::from_static(JSON))
+            .finish();
+        let e = AnyBody::<Info>::from_request(&mut service_request(req, "/", ()))
+            .wait()
+            .unwrap_err();
+        assert_eq!(status(e), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_any_body_format() {
+        #[derive(Deserialize)]
+        struct Record {
+            id: u64,
+            tags: HashMap<u32, String>,
+        }
+
+        let mut cfg = AnyBodyConfig::default();
+        cfg.format("text/x-json", |body, de| {
+            de.deserialize(&mut serde_json::Deserializer::from_slice(body))
+        })
+        .format("text/x-skip", |_, _| Ok(()));
+
+        let body = br#"{"id": 18446744073709551615, "tags": {"1": "a"}}"#;
+        let record =
+            extract::<AnyBody<Record>, _>("text/x-json", body, cfg.clone()).unwrap();
+        assert_eq!(record.id, u64::max_value());
+        assert_eq!(record.tags[&1], "a");
+
+        let e =
+            extract::<AnyBody<Info>, _>("text/x-json", b"{}", cfg.clone()).unwrap_err();
+        assert_eq!(status(e), StatusCode::BAD_REQUEST);
+
+        // decoder did not deserialize the payload
+        let e = extract::<AnyBody<Info>, _>("text/x-skip", JSON, cfg).unwrap_err();
+        assert_eq!(status(e), StatusCode::BAD_REQUEST);
     }
 
     #[derive(Deserialize)]
@@ -1202,34 +2377,29 @@ mod tests {
     #[test]
     fn test_request_extract() {
         let req = TestRequest::with_uri("/name/user1/?id=test").finish();
+        let mut req = service_request(req, "/{key}/{value}/", ());
 
-        let mut router = Router::<()>::default();
-        router.register_resource(Resource::new(ResourceDef::new("/{key}/{value}/")));
-        let info = router.recognize(&req, &(), 0);
-        let req = req.with_route_info(info);
-
-        let s = Path::<MyStruct>::from_request(&req, &()).unwrap();
+        let s = Path::<MyStruct>::from_request(&mut req).wait().unwrap();
         assert_eq!(s.key, "name");
         assert_eq!(s.value, "user1");
 
-        let s = Path::<(String, String)>::from_request(&req, &()).unwrap();
+        let s = Path::<(String, String)>::from_request(&mut req)
+            .wait()
+            .unwrap();
         assert_eq!(s.0, "name");
         assert_eq!(s.1, "user1");
 
-        let s = Query::<Id>::from_request(&req, &()).unwrap();
+        let s = Query::<Id>::from_request(&mut req).wait().unwrap();
         assert_eq!(s.id, "test");
 
-        let mut router = Router::<()>::default();
-        router.register_resource(Resource::new(ResourceDef::new("/{key}/{value}/")));
         let req = TestRequest::with_uri("/name/32/").finish();
-        let info = router.recognize(&req, &(), 0);
-        let req = req.with_route_info(info);
+        let mut req = service_request(req, "/{key}/{value}/", ());
 
-        let s = Path::<Test2>::from_request(&req, &()).unwrap();
+        let s = Path::<Test2>::from_request(&mut req).wait().unwrap();
         assert_eq!(s.as_ref().key, "name");
         assert_eq!(s.value, 32);
 
-        let s = Path::<(String, u8)>::from_request(&req, &()).unwrap();
+        let s = Path::<(String, u8)>::from_request(&mut req).wait().unwrap();
         assert_eq!(s.0, "name");
         assert_eq!(s.1, 32);
 
@@ -1239,43 +2409,47 @@ mod tests {
     }
 
     #[test]
-    fn test_extract_path_single() {
-        let mut router = Router::<()>::default();
-        router.register_resource(Resource::new(ResourceDef::new("/{value}/")));
+    fn test_path_config_decode() {
+        let req = TestRequest::with_uri("/john%20doe/").finish();
+        let req = service_request(req, "/{name}/", PathConfig::default());
+        assert_eq!(*Path::<String>::extract(&req).unwrap(), "john doe");
+        assert_eq!(*Path::<String>::extract_raw(&req).unwrap(), "john%20doe");
+
+        let mut cfg = PathConfig::default();
+        cfg.decode(false);
+        let req = TestRequest::with_uri("/john%20doe/").finish();
+        let req = service_request(req, "/{name}/", cfg);
+        assert_eq!(*Path::<String>::extract(&req).unwrap(), "john%20doe");
+        assert_eq!(*Path::<String>::extract_raw(&req).unwrap(), "john%20doe");
+    }
 
+    #[test]
+    fn test_extract_path_single() {
         let req = TestRequest::with_uri("/32/").finish();
-        let info = router.recognize(&req, &(), 0);
-        let req = req.with_route_info(info);
-        assert_eq!(*Path::<i8>::from_request(&req, &()).unwrap(), 32);
+        let mut req = service_request(req, "/{value}/", ());
+        assert_eq!(*Path::<i8>::from_request(&mut req).wait().unwrap(), 32);
     }
 
     #[test]
     fn test_tuple_extract() {
-        let mut router = Router::<()>::default();
-        router.register_resource(Resource::new(ResourceDef::new("/{key}/{value}/")));
-
         let req = TestRequest::with_uri("/name/user1/?id=test").finish();
-        let info = router.recognize(&req, &(), 0);
-        let req = req.with_route_info(info);
+        let mut req = service_request(req, "/{key}/{value}/", ());
 
-        let res = match <(Path<(String, String)>,)>::extract(&req).poll() {
-            Ok(Async::Ready(res)) => res,
-            _ => panic!("error"),
-        };
+        let res = <(Path<(String, String)>,)>::from_request(&mut req)
+            .wait()
+            .unwrap();
         assert_eq!((res.0).0, "name");
         assert_eq!((res.0).1, "user1");
 
-        let res = match <(Path<(String, String)>, Path<(String, String)>)>::extract(&req)
-            .poll()
-        {
-            Ok(Async::Ready(res)) => res,
-            _ => panic!("error"),
-        };
+        let res =
+            <(Path<(String, String)>, Path<(String, String)>)>::from_request(&mut req)
+                .wait()
+                .unwrap();
         assert_eq!((res.0).0, "name");
         assert_eq!((res.0).1, "user1");
         assert_eq!((res.1).0, "name");
         assert_eq!((res.1).1, "user1");
 
-        let () = <()>::extract(&req);
+        let () = <()>::from_request(&mut req).wait().unwrap();
     }
 }
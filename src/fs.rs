@@ -1,7 +1,10 @@
 //! Static files support
 use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
 use std::fmt::Write;
 use std::fs::{DirEntry, File, Metadata};
+use std::hash::{BuildHasher, Hasher};
 use std::io::{Read, Seek};
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
@@ -499,28 +502,55 @@ impl<C: StaticFileConfig> Responder for NamedFile<C> {
 
         let mut length = self.md.len();
         let mut offset = 0;
+        let mut multipart = None;
 
         // check for range header
         if let Some(ranges) = req.headers().get(header::RANGE) {
             if let Ok(rangesheader) = ranges.to_str() {
-                if let Ok(rangesvec) = HttpRange::parse(rangesheader, length) {
-                    length = rangesvec[0].length;
-                    offset = rangesvec[0].start;
-                    // TODO blocking by compressing
-                    // resp.content_encoding(ContentEncoding::Identity);
-                    resp.header(
-                        header::CONTENT_RANGE,
-                        format!(
-                            "bytes {}-{}/{}",
-                            offset,
-                            offset + length - 1,
-                            self.md.len()
-                        ),
-                    );
-                } else {
-                    resp.header(header::CONTENT_RANGE, format!("bytes */{}", length));
-                    return ok(resp.status(StatusCode::RANGE_NOT_SATISFIABLE).finish());
-                };
+                match HttpRange::parse(rangesheader, length).map(HttpRange::coalesce) {
+                    // too many ranges, probably an attack
+                    Ok(ref rangesvec) if rangesvec.len() > MAX_RANGES => {
+                        resp.header(
+                            header::CONTENT_RANGE,
+                            format!("bytes */{}", length),
+                        );
+                        return ok(resp
+                            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                            .finish());
+                    }
+                    Ok(ref rangesvec) if rangesvec.len() > 1 => {
+                        let parts =
+                            MultipartRanges::new(&self.content_type, rangesvec, length);
+                        length = parts.length;
+                        resp.set(header::ContentType(parts.content_type()));
+                        multipart = Some(parts);
+                    }
+                    Ok(ref rangesvec) if rangesvec.len() == 1 => {
+                        length = rangesvec[0].length;
+                        offset = rangesvec[0].start;
+                        // TODO blocking by compressing
+                        // resp.content_encoding(ContentEncoding::Identity);
+                        resp.header(
+                            header::CONTENT_RANGE,
+                            format!(
+                                "bytes {}-{}/{}",
+                                offset,
+                                offset + length - 1,
+                                self.md.len()
+                            ),
+                        );
+                    }
+                    Ok(_) => (),
+                    Err(_) => {
+                        resp.header(
+                            header::CONTENT_RANGE,
+                            format!("bytes */{}", length),
+                        );
+                        return ok(resp
+                            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                            .finish());
+                    }
+                }
             } else {
                 return ok(resp.status(StatusCode::BAD_REQUEST).finish());
             };
@@ -547,6 +577,11 @@ impl<C: StaticFileConfig> Responder for NamedFile<C> {
                 fut: None,
                 counter: 0,
             };
+            if let Some(parts) = multipart {
+                return ok(resp
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .streaming(parts.into_stream(reader)));
+            }
             if offset != 0 || length != self.md.len() {
                 return ok(resp.status(StatusCode::PARTIAL_CONTENT).streaming(reader));
             };
@@ -610,6 +645,109 @@ impl Stream for ChunkedReadFile {
     }
 }
 
+/// Max number of ranges, after coalescing, served in a single response
+const MAX_RANGES: usize = 32;
+
+/// Parts of the `multipart/byteranges` response
+struct MultipartRanges {
+    boundary: String,
+    parts: VecDeque<(Bytes, HttpRange)>,
+    trailer: Bytes,
+    /// Size of the whole response body
+    length: u64,
+}
+
+impl MultipartRanges {
+    fn new(content_type: &mime::Mime, ranges: &[HttpRange], size: u64) -> Self {
+        let boundary = multipart_boundary();
+        let mut length = 0;
+        let parts: VecDeque<_> = ranges
+            .iter()
+            .enumerate()
+            .map(|(idx, range)| {
+                let head = Bytes::from(format!(
+                    "{}--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                    if idx == 0 { "" } else { "\r\n" },
+                    boundary,
+                    content_type,
+                    range.start,
+                    range.start + range.length - 1,
+                    size
+                ));
+                length += head.len() as u64 + range.length;
+                (head, *range)
+            })
+            .collect();
+        let trailer = Bytes::from(format!("\r\n--{}--\r\n", boundary));
+        length += trailer.len() as u64;
+
+        MultipartRanges {
+            boundary,
+            parts,
+            trailer,
+            length,
+        }
+    }
+
+    fn content_type(&self) -> mime::Mime {
+        format!("multipart/byteranges; boundary={}", self.boundary)
+            .parse()
+            .unwrap()
+    }
+
+    fn into_stream(self, reader: ChunkedReadFile) -> ChunkedReadRanges {
+        ChunkedReadRanges {
+            reader,
+            parts: self.parts,
+            trailer: Some(self.trailer),
+            reading: false,
+        }
+    }
+}
+
+/// Boundary that is unlikely to appear in the file content
+fn multipart_boundary() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u64(now.as_secs());
+        hasher.write_u32(now.subsec_nanos());
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Stream of the `multipart/byteranges` body, each part is read
+/// chunk-by-chunk with `ChunkedReadFile`.
+struct ChunkedReadRanges {
+    reader: ChunkedReadFile,
+    parts: VecDeque<(Bytes, HttpRange)>,
+    trailer: Option<Bytes>,
+    reading: bool,
+}
+
+impl Stream for ChunkedReadRanges {
+    type Item = Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, Error> {
+        if self.reading {
+            match self.reader.poll()? {
+                Async::Ready(None) => self.reading = false,
+                res => return Ok(res),
+            }
+        }
+
+        if let Some((head, range)) = self.parts.pop_front() {
+            self.reader.offset = range.start;
+            self.reader.size = range.length;
+            self.reader.counter = 0;
+            self.reading = true;
+            Ok(Async::Ready(Some(head)))
+        } else {
+            Ok(Async::Ready(self.trailer.take()))
+        }
+    }
+}
+
 type DirectoryRenderer =
     FnOnce(&Directory, &HttpRequest) -> Result<ServiceResponse, io::Error>;
 
@@ -1125,6 +1263,184 @@ impl HttpRange {
 
         Ok(ranges)
     }
+
+    /// Sort ranges and merge overlapping or adjacent ones.
+    fn coalesce(mut ranges: Vec<HttpRange>) -> Vec<HttpRange> {
+        ranges.sort_by_key(|range| range.start);
+
+        let mut result: Vec<HttpRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if let Some(last) = result.last_mut() {
+                let end = last.start + last.length;
+                if range.start <= end {
+                    last.length = cmp::max(end, range.start + range.length) - last.start;
+                    continue;
+                }
+            }
+            result.push(range);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod range_tests {
+    use std::fs;
+    use std::rc::Rc;
+
+    use actix_http::body::{Body, MessageBody, ResponseBody};
+    use actix_http::test::TestRequest;
+    use actix_http::Extensions;
+    use actix_router::Url;
+    use futures::stream;
+
+    use super::*;
+
+    fn request(range: &str) -> HttpRequest {
+        let (head, _) = TestRequest::with_header(header::RANGE, range)
+            .finish()
+            .into_parts();
+        let path = actix_router::Path::new(Url::new(head.uri.clone()));
+        HttpRequest::new(head, path, Rc::new(Extensions::new()))
+    }
+
+    fn read_body(res: Response) -> Bytes {
+        let mut body = None;
+        res.map_body(|_, b| {
+            body = Some(b);
+            ResponseBody::Other(Body::Empty)
+        });
+        let mut body = body.unwrap();
+        stream::poll_fn(move || body.poll_next())
+            .concat2()
+            .wait()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let ranges = HttpRange::parse("bytes=50-59,0-9,5-14,15-19,90-", 100).unwrap();
+        let ranges: Vec<_> = HttpRange::coalesce(ranges)
+            .iter()
+            .map(|r| (r.start, r.length))
+            .collect();
+        assert_eq!(ranges, vec![(0, 20), (50, 10), (90, 10)]);
+    }
+
+    #[test]
+    fn test_multipart_ranges_length() {
+        let ranges = [
+            HttpRange {
+                start: 0,
+                length: 10,
+            },
+            HttpRange {
+                start: 20,
+                length: 5,
+            },
+        ];
+        let parts = MultipartRanges::new(&mime::TEXT_PLAIN, &ranges, 100);
+        let body_len: u64 = parts
+            .parts
+            .iter()
+            .map(|(head, range)| head.len() as u64 + range.length)
+            .sum::<u64>()
+            + parts.trailer.len() as u64;
+        assert_eq!(parts.length, body_len);
+        assert!(parts.parts[1]
+            .0
+            .starts_with(format!("\r\n--{}\r\n", parts.boundary).as_bytes()));
+        assert!(parts.parts[1]
+            .0
+            .ends_with(b"Content-Range: bytes 20-24/100\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_multipart_ranges_response() {
+        let data = fs::read("Cargo.toml").unwrap();
+        let size = data.len();
+        let file = NamedFile::open("Cargo.toml")
+            .unwrap()
+            .set_cpu_pool(CpuPool::new(1));
+        let res = file
+            .respond_to(&request("bytes=20-29,0-9,5-14"))
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+
+        let ct = res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert!(ct.starts_with("multipart/byteranges; boundary="));
+        let boundary = &ct["multipart/byteranges; boundary=".len()..];
+        let length: usize = res
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        // overlapping ranges are merged, parts are ordered by offset
+        let file_ct = guess_mime_type("Cargo.toml");
+        let mut expected = Vec::new();
+        expected.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Range: bytes 0-14/{}\r\n\r\n",
+                boundary, file_ct, size
+            )
+            .as_bytes(),
+        );
+        expected.extend_from_slice(&data[0..15]);
+        expected.extend_from_slice(
+            format!(
+                "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes 20-29/{}\r\n\r\n",
+                boundary, file_ct, size
+            )
+            .as_bytes(),
+        );
+        expected.extend_from_slice(&data[20..30]);
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        let body = read_body(res);
+        assert_eq!(body.len(), length);
+        assert_eq!(body, Bytes::from(expected));
+    }
+
+    #[test]
+    fn test_too_many_ranges() {
+        let size = fs::metadata("Cargo.toml").unwrap().len();
+        let ranges: Vec<_> = (0..=MAX_RANGES)
+            .map(|idx| format!("{}-{}", idx * 2, idx * 2))
+            .collect();
+
+        let file = NamedFile::open("Cargo.toml").unwrap();
+        let res = file
+            .respond_to(&request(&format!("bytes={}", ranges.join(","))))
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            res.headers().get(header::CONTENT_RANGE).unwrap(),
+            format!("bytes */{}", size).as_str()
+        );
+
+        // adjacent ranges are merged before counting
+        let ranges: Vec<_> = (0..=MAX_RANGES)
+            .map(|idx| format!("{}-{}", idx, idx))
+            .collect();
+        let file = NamedFile::open("Cargo.toml").unwrap();
+        let res = file
+            .respond_to(&request(&format!("bytes={}", ranges.join(","))))
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    }
 }
 
 #[cfg(test)]
@@ -1,66 +1,69 @@
 use std::cell::Ref;
+use std::cmp;
+use std::net::{IpAddr, SocketAddr};
 
 use actix_http::http::header::{self, HeaderName};
-use actix_http::RequestHead;
+use actix_http::{Error, RequestHead};
+use futures::future::{ok, FutureResult};
+
+use crate::handler::FromRequest;
+use crate::request::HttpRequest;
+use crate::service::ServiceRequest;
 
 const X_FORWARDED_FOR: &[u8] = b"x-forwarded-for";
 const X_FORWARDED_HOST: &[u8] = b"x-forwarded-host";
 const X_FORWARDED_PROTO: &[u8] = b"x-forwarded-proto";
 
-pub enum ConnectionInfoError {
-    UnknownHost,
-    UnknownScheme,
-}
-
 /// `HttpRequest` connection information
-#[derive(Clone, Default)]
+///
+/// `Forwarded` and `X-Forwarded-*` headers are honoured only if request
+/// is received from a trusted proxy, see
+/// [**ConnectionInfoConfig**](struct.ConnectionInfoConfig.html).
+#[derive(Clone, Debug)]
 pub struct ConnectionInfo {
     scheme: String,
     host: String,
     remote: Option<String>,
-    peer: Option<String>,
+    peer: Option<SocketAddr>,
 }
 
 impl ConnectionInfo {
     /// Create *ConnectionInfo* instance for a request.
-    pub fn get(req: &RequestHead) -> Ref<Self> {
+    pub fn get(req: &HttpRequest) -> Ref<Self> {
         if !req.extensions().contains::<ConnectionInfo>() {
-            req.extensions_mut().insert(ConnectionInfo::new(req));
+            let info = if let Some(cfg) = req.app_extensions().get() {
+                ConnectionInfo::new(req.head(), cfg)
+            } else {
+                ConnectionInfo::new(req.head(), &ConnectionInfoConfig::default())
+            };
+            req.extensions_mut().insert(info);
         }
         Ref::map(req.extensions(), |e| e.get().unwrap())
     }
 
-    #[cfg_attr(feature = "cargo-clippy", allow(cyclomatic_complexity))]
-    fn new(req: &RequestHead) -> ConnectionInfo {
+    #[allow(clippy::cyclomatic_complexity)]
+    fn new(req: &RequestHead, cfg: &ConnectionInfoConfig) -> ConnectionInfo {
         let mut host = None;
         let mut scheme = None;
         let mut remote = None;
-        let mut peer = None;
-
-        // load forwarded header
-        for hdr in req.headers.get_all(header::FORWARDED) {
-            if let Ok(val) = hdr.to_str() {
-                for pair in val.split(';') {
-                    for el in pair.split(',') {
-                        let mut items = el.trim().splitn(2, '=');
-                        if let Some(name) = items.next() {
-                            if let Some(val) = items.next() {
-                                match &name.to_lowercase() as &str {
-                                    "for" => {
-                                        if remote.is_none() {
-                                            remote = Some(val.trim());
-                                        }
-                                    }
-                                    "proto" => {
-                                        if scheme.is_none() {
-                                            scheme = Some(val.trim());
-                                        }
-                                    }
-                                    "host" => {
-                                        if host.is_none() {
-                                            host = Some(val.trim());
-                                        }
-                                    }
+        let peer = req.peer_addr;
+
+        if cfg.is_trusted(peer.map(|addr| addr.ip())) {
+            let mut chain = Vec::new();
+
+            // load forwarded header
+            for hdr in req.headers.get_all(header::FORWARDED) {
+                if let Ok(val) = hdr.to_str() {
+                    for el in val.split(',') {
+                        for pair in el.split(';') {
+                            let mut items = pair.trim().splitn(2, '=');
+                            if let (Some(name), Some(val)) = (items.next(), items.next())
+                            {
+                                let val = val.trim().trim_matches('"');
+                                match &name.trim().to_lowercase() as &str {
+                                    "for" => chain.push(val),
+                                    "proto" => scheme = Some(val),
+                                    "host" => host = Some(val),
                                     _ => (),
                                 }
                             }
@@ -68,70 +71,70 @@ impl ConnectionInfo {
                     }
                 }
             }
-        }
 
-        // scheme
-        if scheme.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    scheme = h.split(',').next().map(|v| v.trim());
+            // x-forwarded-for
+            if chain.is_empty() {
+                for hdr in req
+                    .headers
+                    .get_all(HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
+                {
+                    if let Ok(val) = hdr.to_str() {
+                        chain.extend(val.split(',').map(|v| v.trim()));
+                    }
                 }
             }
+            remote = cfg.proxies.resolve(&chain);
+
+            // scheme
             if scheme.is_none() {
-                scheme = req.uri.scheme_part().map(|a| a.as_str());
-                if scheme.is_none() && req.server_settings().secure() {
-                    scheme = Some("https")
+                if let Some(h) = req
+                    .headers
+                    .get(HeaderName::from_lowercase(X_FORWARDED_PROTO).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        scheme = h.rsplit(',').next().map(|v| v.trim());
+                    }
                 }
             }
-        }
 
-        // host
-        if host.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    host = h.split(',').next().map(|v| v.trim());
-                }
-            }
+            // host
             if host.is_none() {
-                if let Some(h) = req.headers.get(header::HOST) {
-                    host = h.to_str().ok();
-                }
-                if host.is_none() {
-                    host = req.uri.authority_part().map(|a| a.as_str());
-                    if host.is_none() {
-                        host = Some(req.server_settings().host());
+                if let Some(h) = req
+                    .headers
+                    .get(HeaderName::from_lowercase(X_FORWARDED_HOST).unwrap())
+                {
+                    if let Ok(h) = h.to_str() {
+                        host = h.rsplit(',').next().map(|v| v.trim());
                     }
                 }
             }
         }
 
-        // remote addr
-        if remote.is_none() {
-            if let Some(h) = req
-                .headers
-                .get(HeaderName::from_lowercase(X_FORWARDED_FOR).unwrap())
-            {
-                if let Ok(h) = h.to_str() {
-                    remote = h.split(',').next().map(|v| v.trim());
-                }
+        // scheme
+        if scheme.is_none() {
+            scheme = req.uri.scheme_part().map(|a| a.as_str());
+            if scheme.is_none() && cfg.secure {
+                scheme = Some("https")
             }
-            if remote.is_none() {
-                // get peeraddr from socketaddr
-                peer = req.peer_addr().map(|addr| format!("{}", addr));
+        }
+
+        // host
+        if host.is_none() {
+            if let Some(h) = req.headers.get(header::HOST) {
+                host = h.to_str().ok();
+            }
+            if host.is_none() {
+                host = req.uri.authority_part().map(|a| a.as_str());
             }
         }
 
         ConnectionInfo {
             scheme: scheme.unwrap_or("http").to_owned(),
-            host: host.unwrap_or("localhost").to_owned(),
-            remote: remote.map(|s| s.to_owned()),
-            peer: peer,
+            host: host.unwrap_or(cfg.host.as_str()).to_owned(),
+            remote: remote
+                .map(|s| s.to_owned())
+                .or_else(|| peer.map(|addr| addr.ip().to_string())),
+            peer,
         }
     }
 
@@ -139,9 +142,10 @@ impl ConnectionInfo {
     ///
     /// Scheme is resolved through the following headers, in this order:
     ///
-    /// - Forwarded
-    /// - X-Forwarded-Proto
+    /// - Forwarded (trusted proxy only)
+    /// - X-Forwarded-Proto (trusted proxy only)
     /// - Uri
+    /// - `ConnectionInfoConfig::secure()`
     #[inline]
     pub fn scheme(&self) -> &str {
         &self.scheme
@@ -151,11 +155,11 @@ impl ConnectionInfo {
     ///
     /// Hostname is resolved through the following headers, in this order:
     ///
-    /// - Forwarded
-    /// - X-Forwarded-Host
+    /// - Forwarded (trusted proxy only)
+    /// - X-Forwarded-Host (trusted proxy only)
     /// - Host
     /// - Uri
-    /// - Server hostname
+    /// - `ConnectionInfoConfig::host()`
     pub fn host(&self) -> &str {
         &self.host
     }
@@ -164,17 +168,225 @@ impl ConnectionInfo {
     ///
     /// The IP is resolved through the following headers, in this order:
     ///
-    /// - Forwarded
-    /// - X-Forwarded-For
+    /// - Forwarded (trusted proxy only)
+    /// - X-Forwarded-For (trusted proxy only)
     /// - peer name of opened socket
     #[inline]
     pub fn remote(&self) -> Option<&str> {
-        if let Some(ref r) = self.remote {
-            Some(r)
-        } else if let Some(ref peer) = self.peer {
-            Some(peer)
-        } else {
+        self.remote.as_ref().map(|s| s.as_str())
+    }
+
+    /// Socket address of the connection peer.
+    ///
+    /// This is the address of the proxy if the request went through one.
+    #[inline]
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+}
+
+impl<P> FromRequest<P> for ConnectionInfo {
+    type Error = Error;
+    type Future = FutureResult<Self, Error>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        ok(ConnectionInfo::get(req).clone())
+    }
+}
+
+/// `ConnectionInfo` configuration
+///
+/// By default proxy headers are ignored and remote address is the
+/// socket's peer address.
+///
+/// ```rust
+/// use actix_web2::{dev, App};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::ConnectionInfoConfig| {
+///         cfg.host("www.rust-lang.org")
+///             .trusted_proxies(&["10.0.0.0/8", "192.168.0.1"]);
+///     });
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConnectionInfoConfig {
+    host: String,
+    secure: bool,
+    proxies: TrustedProxies,
+}
+
+impl ConnectionInfoConfig {
+    /// Set server host name, it is used if request does not contain
+    /// host information. By default host name is `localhost`.
+    pub fn host<T: Into<String>>(&mut self, host: T) -> &mut Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Use `https` scheme if request does not contain scheme information.
+    pub fn secure(&mut self, secure: bool) -> &mut Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Trust proxy headers of peers from the specified networks.
+    ///
+    /// Each item is an ip address or a network in CIDR notation,
+    /// i.e. `10.0.0.0/8`. Client address is the right-most address
+    /// in the forwarded chain that does not belong to a trusted network.
+    ///
+    /// Panics if an item can not be parsed.
+    pub fn trusted_proxies<I, T>(&mut self, networks: I) -> &mut Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let networks = networks
+            .into_iter()
+            .map(|net| match Cidr::parse(net.as_ref()) {
+                Some(net) => net,
+                None => panic!("Can not parse network: {}", net.as_ref()),
+            })
+            .collect();
+        self.proxies = TrustedProxies::Networks(networks);
+        self
+    }
+
+    /// Trust proxy headers of specified number of proxies in front of
+    /// the server, regardless of their addresses.
+    ///
+    /// Client address is the `hops`-th address from the right
+    /// of the forwarded chain. Zero disables proxy headers.
+    pub fn trusted_hops(&mut self, hops: usize) -> &mut Self {
+        self.proxies = TrustedProxies::Hops(hops);
+        self
+    }
+
+    /// Trust proxy headers of any peer.
+    ///
+    /// Client is able to spoof its address, this setting should be used only if
+    /// server is not reachable directly.
+    pub fn trust_any_proxy(&mut self) -> &mut Self {
+        self.proxies = TrustedProxies::Any;
+        self
+    }
+
+    fn is_trusted(&self, peer: Option<IpAddr>) -> bool {
+        match self.proxies {
+            TrustedProxies::Any => true,
+            TrustedProxies::Hops(hops) => hops > 0,
+            TrustedProxies::Networks(ref nets) => {
+                if let Some(ip) = peer {
+                    nets.iter().any(|net| net.contains(&ip))
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+impl Default for ConnectionInfoConfig {
+    fn default() -> Self {
+        ConnectionInfoConfig {
+            host: "localhost".to_owned(),
+            secure: false,
+            proxies: TrustedProxies::Hops(0),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum TrustedProxies {
+    Any,
+    Hops(usize),
+    Networks(Vec<Cidr>),
+}
+
+impl TrustedProxies {
+    /// Find client address in the forwarded chain, `chain` is ordered from
+    /// client to the closest proxy.
+    fn resolve<'a>(&self, chain: &[&'a str]) -> Option<&'a str> {
+        if chain.is_empty() {
+            return None;
+        }
+        match *self {
+            TrustedProxies::Any => Some(chain[0]),
+            TrustedProxies::Hops(hops) => {
+                Some(chain[chain.len().saturating_sub(cmp::max(hops, 1))])
+            }
+            TrustedProxies::Networks(ref nets) => {
+                for addr in chain.iter().rev() {
+                    match parse_node(addr) {
+                        Some(ip) if nets.iter().any(|net| net.contains(&ip)) => (),
+                        _ => return Some(addr),
+                    }
+                }
+                Some(chain[0])
+            }
+        }
+    }
+}
+
+/// Parse forwarded node, i.e. `192.0.2.43`, `192.0.2.43:47011`
+/// or `[2001:db8:cafe::17]:4711`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim_matches('"');
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        Some(ip)
+    } else if let Ok(addr) = node.parse::<SocketAddr>() {
+        Some(addr.ip())
+    } else if node.starts_with('[') {
+        node[1..].split(']').next().and_then(|ip| ip.parse().ok())
+    } else {
+        None
+    }
+}
+
+/// Ip network
+#[derive(Clone, Debug, PartialEq)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(s: &str) -> Option<Cidr> {
+        let mut parts = s.trim().splitn(2, '/');
+        let addr: IpAddr = parts.next()?.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            Some(prefix) => prefix.parse().ok()?,
+            None => max,
+        };
+        if prefix > max {
             None
+        } else {
+            Some(Cidr { addr, prefix })
+        }
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::max_value()
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::max_value()
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            (IpAddr::V4(_), IpAddr::V6(ip)) => match ip.to_ipv4() {
+                Some(ip) => self.contains(&IpAddr::V4(ip)),
+                None => false,
+            },
+            _ => false,
         }
     }
 }
@@ -182,59 +394,139 @@ impl ConnectionInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test::TestRequest;
+    use actix_http::test::TestRequest;
 
-    #[test]
-    fn test_forwarded() {
-        let req = TestRequest::default().request();
-        let mut info = ConnectionInfo::default();
-        info.update(&req);
-        assert_eq!(info.scheme(), "http");
-        assert_eq!(info.host(), "localhost:8080");
+    fn peer(addr: &str) -> Option<SocketAddr> {
+        Some(addr.parse().unwrap())
+    }
 
-        let req = TestRequest::default()
+    #[test]
+    fn test_default() {
+        let cfg = ConnectionInfoConfig::default();
+        let mut req = TestRequest::default()
             .header(
                 header::FORWARDED,
                 "for=192.0.2.60; proto=https; by=203.0.113.43; host=rust-lang.org",
             )
-            .request();
+            .header(X_FORWARDED_FOR, "192.0.2.61")
+            .finish();
+        req.head_mut().peer_addr = peer("127.0.0.1:8080");
 
-        let mut info = ConnectionInfo::default();
-        info.update(&req);
-        assert_eq!(info.scheme(), "https");
-        assert_eq!(info.host(), "rust-lang.org");
-        assert_eq!(info.remote(), Some("192.0.2.60"));
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "localhost");
+        assert_eq!(info.remote(), Some("127.0.0.1"));
 
         let req = TestRequest::default()
             .header(header::HOST, "rust-lang.org")
-            .request();
-
-        let mut info = ConnectionInfo::default();
-        info.update(&req);
+            .finish();
+        let info = ConnectionInfo::new(req.head(), &cfg);
         assert_eq!(info.scheme(), "http");
         assert_eq!(info.host(), "rust-lang.org");
         assert_eq!(info.remote(), None);
 
-        let req = TestRequest::default()
-            .header(X_FORWARDED_FOR, "192.0.2.60")
-            .request();
-        let mut info = ConnectionInfo::default();
-        info.update(&req);
-        assert_eq!(info.remote(), Some("192.0.2.60"));
+        let mut cfg = ConnectionInfoConfig::default();
+        cfg.host("www.rust-lang.org").secure(true);
+        let req = TestRequest::default().finish();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "www.rust-lang.org");
+    }
+
+    #[test]
+    fn test_forwarded() {
+        let mut cfg = ConnectionInfoConfig::default();
+        cfg.trust_any_proxy();
 
         let req = TestRequest::default()
-            .header(X_FORWARDED_HOST, "192.0.2.60")
-            .request();
-        let mut info = ConnectionInfo::default();
-        info.update(&req);
-        assert_eq!(info.host(), "192.0.2.60");
-        assert_eq!(info.remote(), None);
+            .header(
+                header::FORWARDED,
+                "for=192.0.2.60; proto=https; by=203.0.113.43; host=rust-lang.org",
+            )
+            .finish();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.remote(), Some("192.0.2.60"));
 
         let req = TestRequest::default()
+            .header(X_FORWARDED_FOR, "192.0.2.60")
+            .header(X_FORWARDED_HOST, "192.0.2.61")
             .header(X_FORWARDED_PROTO, "https")
-            .request();
-        let mut info = ConnectionInfo::default();
-        info.update(&req);
+            .finish();
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+        assert_eq!(info.host(), "192.0.2.61");
         assert_eq!(info.scheme(), "https");
     }
+
+    #[test]
+    fn test_trusted_hops() {
+        let mut cfg = ConnectionInfoConfig::default();
+        cfg.trusted_hops(1);
+
+        let mut req = TestRequest::default()
+            .header(X_FORWARDED_FOR, "10.0.0.1, 192.0.2.60")
+            .finish();
+        req.head_mut().peer_addr = peer("127.0.0.1:8080");
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+
+        cfg.trusted_hops(2);
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.remote(), Some("10.0.0.1"));
+
+        cfg.trusted_hops(5);
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.remote(), Some("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let mut cfg = ConnectionInfoConfig::default();
+        cfg.trusted_proxies(&["10.0.0.0/8", "::1"]);
+
+        let mut req = TestRequest::default()
+            .header(
+                header::FORWARDED,
+                "for=192.0.2.43, for=\"[2001:db8:cafe::17]:4711\", for=10.1.1.1",
+            )
+            .header(header::HOST, "rust-lang.org")
+            .finish();
+
+        // untrusted peer
+        req.head_mut().peer_addr = peer("192.0.2.1:8080");
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.remote(), Some("192.0.2.1"));
+        assert_eq!(info.peer_addr(), peer("192.0.2.1:8080"));
+
+        // trusted peer
+        req.head_mut().peer_addr = peer("10.0.0.2:8080");
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.remote(), Some("[2001:db8:cafe::17]:4711"));
+        assert_eq!(info.host(), "rust-lang.org");
+
+        req.head_mut().peer_addr = peer("[::1]:8080");
+        let info = ConnectionInfo::new(req.head(), &cfg);
+        assert_eq!(info.remote(), Some("[2001:db8:cafe::17]:4711"));
+    }
+
+    #[test]
+    fn test_cidr() {
+        let net = Cidr::parse("192.168.0.0/16").unwrap();
+        assert!(net.contains(&"192.168.10.1".parse().unwrap()));
+        assert!(net.contains(&"::ffff:192.168.10.1".parse().unwrap()));
+        assert!(!net.contains(&"192.169.0.1".parse().unwrap()));
+        assert!(!net.contains(&"::1".parse().unwrap()));
+
+        let net = Cidr::parse("0.0.0.0/0").unwrap();
+        assert!(net.contains(&"8.8.8.8".parse().unwrap()));
+
+        let net = Cidr::parse("2001:db8::/32").unwrap();
+        assert!(net.contains(&"2001:db8:cafe::17".parse().unwrap()));
+        assert!(!net.contains(&"2001:db9::1".parse().unwrap()));
+
+        assert!(Cidr::parse("10.0.0.0/33").is_none());
+        assert!(Cidr::parse("localhost").is_none());
+    }
 }
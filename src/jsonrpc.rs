@@ -0,0 +1,737 @@
+//! JSON-RPC 2.0 service
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use actix_http::body::{self, Body, ResponseBody};
+use actix_http::dev::MessageBody;
+use actix_http::error::{Error, PayloadError, ResponseError};
+use actix_http::http::{header, Method};
+use actix_http::Response;
+use actix_service::{NewService, Service};
+use bytes::{Bytes, BytesMut};
+use futures::future::{self, err, ok, Either};
+use futures::{Async, Future, IntoFuture, Poll, Stream};
+use serde::de::DeserializeOwned;
+use serde_json::{self, json, Value};
+
+use crate::extractor::{buffered_payload, load_config};
+use crate::handler::{AsyncFactory, Factory, FromRequest};
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+use crate::route::{Route, RouteService};
+use crate::service::{ServiceRequest, ServiceResponse};
+
+/// Invalid JSON was received by the server
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON sent is not a valid Request object
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist / is not available
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameter(s)
+pub const INVALID_PARAMS: i64 = -32602;
+/// Internal JSON-RPC error
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// JSON-RPC service
+///
+/// Dispatches single and batch calls to the registered methods. Calls of
+/// the batch are processed concurrently. Notifications, calls without
+/// `id`, are processed but get no response, *204 No Content* is returned
+/// if there is nothing to respond with.
+///
+/// Method handlers are regular handlers, parameters of the call are
+/// extracted with [`Params`](struct.Params.html) extractor. Size of the
+/// request is limited with [`JsonRpcConfig`](dev/struct.JsonRpcConfig.html).
+/// Successful handler's response body becomes the call's `result`,
+/// `application/json` body is parsed, any other body is returned as a
+/// string. Handler could fail with [`RpcError`](struct.RpcError.html) to
+/// return specific error object.
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{App, Json, JsonRpc, Params, RpcError};
+///
+/// #[derive(Deserialize)]
+/// struct Range {
+///     from: i64,
+///     to: i64,
+/// }
+///
+/// /// positional parameters, i.e. `[1, 2]`
+/// fn add(params: Params<(i64, i64)>) -> Json<i64> {
+///     Json((params.0).0 + (params.0).1)
+/// }
+///
+/// /// named parameters, i.e. `{"from": 1, "to": 10}`
+/// fn sum(range: Params<Range>) -> Result<Json<i64>, RpcError> {
+///     if range.from > range.to {
+///         return Err(RpcError::new(1, "Empty range"));
+///     }
+///     Ok(Json((range.from..=range.to).sum()))
+/// }
+///
+/// fn main() {
+///     let app = App::new().service(
+///         "/rpc",
+///         JsonRpc::new().method("add", add).method("sum", sum),
+///     );
+/// }
+/// ```
+pub struct JsonRpc<P> {
+    methods: Vec<(String, Route<P>)>,
+}
+
+impl<P: 'static> JsonRpc<P> {
+    /// Create service without methods
+    pub fn new() -> Self {
+        JsonRpc {
+            methods: Vec::new(),
+        }
+    }
+
+    /// Register method handler, use request extractors for parameters.
+    pub fn method<F, T, R>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Factory<T, R> + 'static,
+        T: FromRequest<P> + 'static,
+        R: Responder + 'static,
+    {
+        self.methods
+            .push((name.to_owned(), Route::build().to(handler)));
+        self
+    }
+
+    /// Register async method handler, use request extractors for parameters.
+    pub fn method_async<F, T, R>(mut self, name: &str, handler: F) -> Self
+    where
+        F: AsyncFactory<T, R>,
+        T: FromRequest<P> + 'static,
+        R: IntoFuture + 'static,
+        R::Item: Into<Response>,
+        R::Error: Into<Error>,
+    {
+        self.methods
+            .push((name.to_owned(), Route::build().to_async(handler)));
+        self
+    }
+}
+
+impl<P: 'static> Default for JsonRpc<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> NewService for JsonRpc<P>
+where
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Request = ServiceRequest<P>;
+    type Response = ServiceResponse;
+    type Error = ();
+    type InitError = ();
+    type Service = JsonRpcService<P>;
+    type Future = Box<Future<Item = Self::Service, Error = ()>>;
+
+    fn new_service(&self) -> Self::Future {
+        let methods = self.methods.iter().map(|(name, route)| {
+            let name = name.clone();
+            route.new_service().map(move |srv| (name, srv))
+        });
+
+        Box::new(
+            future::join_all(methods).map(move |methods| JsonRpcService {
+                methods: Rc::new(RefCell::new(methods.into_iter().collect())),
+            }),
+        )
+    }
+}
+
+/// Service that dispatches calls to the methods of
+/// [`JsonRpc`](../struct.JsonRpc.html)
+pub struct JsonRpcService<P> {
+    methods: Rc<RefCell<HashMap<String, RouteService<P>>>>,
+}
+
+impl<P> Service for JsonRpcService<P>
+where
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Request = ServiceRequest<P>;
+    type Response = ServiceResponse;
+    type Error = ();
+    type Future = Box<Future<Item = ServiceResponse, Error = ()>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(&mut self, mut req: ServiceRequest<P>) -> Self::Future {
+        if *req.method() != Method::POST {
+            let res = Response::MethodNotAllowed()
+                .header(header::ALLOW, "POST")
+                .finish();
+            return Box::new(ok(ServiceResponse::new(req.into_request(), res)));
+        }
+
+        let cfg = load_config::<JsonRpcConfig>(&req);
+        let methods = self.methods.clone();
+        let req2 = req.request().clone();
+        Box::new(
+            MessageBody::new(&mut req)
+                .limit(cfg.limit)
+                .then(move |body| {
+                    let body = match body {
+                        Ok(body) => body,
+                        Err(e) => {
+                            let res: Response = Error::from(e).into();
+                            return Either::A(ok(ServiceResponse::new(req2, res)));
+                        }
+                    };
+
+                    let fut = match serde_json::from_slice::<Value>(&body) {
+                        Err(_) => Either::A(ok(Some(error_response(
+                            Value::Null,
+                            RpcError::new(PARSE_ERROR, "Parse error"),
+                        )))),
+                        Ok(Value::Array(ref calls)) if calls.is_empty() => {
+                            Either::A(ok(Some(error_response(
+                                Value::Null,
+                                RpcError::new(INVALID_REQUEST, "Invalid Request"),
+                            ))))
+                        }
+                        Ok(Value::Array(calls)) => {
+                            let calls = calls
+                                .into_iter()
+                                .map(|call| dispatch(&methods, &req2, call));
+                            Either::B(Either::A(future::join_all(calls).map(|res| {
+                                let res: Vec<_> =
+                                    res.into_iter().filter_map(|r| r).collect();
+                                if res.is_empty() {
+                                    None
+                                } else {
+                                    Some(Value::Array(res))
+                                }
+                            })))
+                        }
+                        Ok(call) => {
+                            Either::B(Either::B(dispatch(&methods, &req2, call)))
+                        }
+                    };
+
+                    Either::B(fut.map(move |res| {
+                        let res = match res {
+                            Some(res) => Response::Ok()
+                                .content_type("application/json")
+                                .body(res.to_string()),
+                            None => Response::NoContent().finish(),
+                        };
+                        ServiceResponse::new(req2, res)
+                    }))
+                }),
+        )
+    }
+}
+
+/// Process single call, resolves to response object
+/// or `None` for notifications
+fn dispatch<P: 'static>(
+    methods: &Rc<RefCell<HashMap<String, RouteService<P>>>>,
+    req: &HttpRequest,
+    call: Value,
+) -> Box<Future<Item = Option<Value>, Error = ()>> {
+    let (id, method, params) = match parse_call(call) {
+        Ok(call) => call,
+        Err(id) => {
+            return Box::new(ok(Some(error_response(
+                id,
+                RpcError::new(INVALID_REQUEST, "Invalid Request"),
+            ))));
+        }
+    };
+
+    let params = Bytes::from(params.to_string());
+    let fut = methods.borrow_mut().get_mut(&method).map(|srv| {
+        srv.call(ServiceRequest::from_parts(
+            req.clone(),
+            buffered_payload(&params),
+        ))
+    });
+    let fut = match fut {
+        Some(fut) => fut,
+        None => {
+            return Box::new(ok(id.map(|id| {
+                error_response(id, RpcError::new(METHOD_NOT_FOUND, "Method not found"))
+            })));
+        }
+    };
+
+    Box::new(fut.and_then(read_response).then(move |res| {
+        let res =
+            res.unwrap_or_else(|_| Err(RpcError::new(INTERNAL_ERROR, "Internal error")));
+        Ok::<_, ()>(id.map(|id| match res {
+            Ok(result) => json!({"jsonrpc": "2.0", "result": result, "id": id}),
+            Err(e) => error_response(id, e),
+        }))
+    }))
+}
+
+/// Validate request object, returns `id` of the invalid request on error.
+///
+/// `id` is `None` for notifications.
+fn parse_call(call: Value) -> Result<(Option<Value>, String, Value), Value> {
+    let mut call = match call {
+        Value::Object(call) => call,
+        _ => return Err(Value::Null),
+    };
+
+    let id = match call.remove("id") {
+        None => None,
+        Some(id) => match id {
+            Value::Null | Value::Number(_) | Value::String(_) => Some(id),
+            _ => return Err(Value::Null),
+        },
+    };
+    let err_id = || id.clone().unwrap_or(Value::Null);
+
+    if call.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+        return Err(err_id());
+    }
+    let method = match call.remove("method") {
+        Some(Value::String(method)) => method,
+        _ => return Err(err_id()),
+    };
+    let params = match call.remove("params") {
+        None => Value::Null,
+        Some(params) => match params {
+            Value::Array(_) | Value::Object(_) => params,
+            _ => return Err(err_id()),
+        },
+    };
+
+    Ok((id, method, params))
+}
+
+/// Convert method's response to the call's result or error
+fn read_response(
+    res: ServiceResponse,
+) -> impl Future<Item = Result<Value, RpcError>, Error = ()> {
+    let json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .and_then(|ct| ct.parse::<mime::Mime>().ok())
+        .map_or(false, |ct| {
+            ct.type_() == mime::APPLICATION && ct.subtype() == mime::JSON
+        });
+
+    let mut body = None;
+    let res = res.map_body(|_, b| {
+        body = Some(b);
+        ResponseBody::Other(Body::Empty)
+    });
+    let status = res.status();
+    let mut body = body.unwrap();
+    let mut buf = BytesMut::new();
+
+    future::poll_fn(move || loop {
+        match body::MessageBody::poll_next(&mut body).map_err(|_| ())? {
+            Async::Ready(Some(chunk)) => buf.extend_from_slice(&chunk),
+            Async::Ready(None) => return Ok(Async::Ready(buf.take().freeze())),
+            Async::NotReady => return Ok(Async::NotReady),
+        }
+    })
+    .map(move |body| {
+        if status.is_success() {
+            if body.is_empty() {
+                Ok(Value::Null)
+            } else if json {
+                Ok(serde_json::from_slice(&body).unwrap_or_else(|_| {
+                    Value::String(String::from_utf8_lossy(&body).into_owned())
+                }))
+            } else {
+                Ok(Value::String(String::from_utf8_lossy(&body).into_owned()))
+            }
+        } else {
+            Err(RpcError::from_slice(&body).unwrap_or_else(|| {
+                RpcError::new(
+                    INTERNAL_ERROR,
+                    status.canonical_reason().unwrap_or("Internal error"),
+                )
+            }))
+        }
+    })
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({"jsonrpc": "2.0", "error": err.to_value(), "id": id})
+}
+
+/// JSON-RPC error object
+///
+/// Could be returned from method handler, `code` and `message` are
+/// passed to the client as is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    /// Create error object
+    pub fn new<T: Into<String>>(code: i64, message: T) -> RpcError {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Set additional information about the error
+    pub fn data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Error code
+    pub fn code(&self) -> i64 {
+        self.code
+    }
+
+    /// Error message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn to_value(&self) -> Value {
+        let mut err = json!({"code": self.code, "message": self.message});
+        if let Some(ref data) = self.data {
+            err["data"] = data.clone();
+        }
+        err
+    }
+
+    fn from_slice(body: &[u8]) -> Option<RpcError> {
+        let mut err: Value = serde_json::from_slice(body).ok()?;
+        Some(RpcError {
+            code: err.get("code")?.as_i64()?,
+            message: err.get("message")?.as_str()?.to_owned(),
+            data: err.get_mut("data").map(Value::take),
+        })
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+/// Return `BadRequest` with error object
+impl ResponseError for RpcError {
+    fn error_response(&self) -> Response {
+        Response::BadRequest()
+            .content_type("application/json")
+            .body(self.to_value().to_string())
+    }
+}
+
+/// JSON-RPC service configuration
+///
+/// Limit applies to the whole request, including batches, and to the
+/// payload read by the [`Params`](../struct.Params.html) extractor.
+///
+/// ```rust
+/// use actix_web2::dev::JsonRpcConfig;
+/// use actix_web2::App;
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut JsonRpcConfig| {
+///         cfg.limit(4096);
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct JsonRpcConfig {
+    limit: usize,
+}
+
+impl JsonRpcConfig {
+    /// Change max size of payload. By default max size is 256Kb
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for JsonRpcConfig {
+    fn default() -> Self {
+        JsonRpcConfig { limit: 262_144 }
+    }
+}
+
+/// Extract parameters of the JSON-RPC call
+///
+/// Positional parameters could be deserialized to a tuple or a sequence,
+/// named parameters to a struct or a map. Missing parameters are
+/// deserialized from `null`, i.e. to `()` or `Option<T>`. Deserialization
+/// error is returned to the client as *Invalid params* error.
+pub struct Params<T>(pub T);
+
+impl<T> Params<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Params<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Params<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Params<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T, P> FromRequest<P> for Params<T>
+where
+    T: DeserializeOwned + 'static,
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Error = Error;
+    type Future = Box<Future<Item = Self, Error = Error>>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        let cfg = load_config::<JsonRpcConfig>(req);
+        Box::new(
+            MessageBody::new(req)
+                .limit(cfg.limit)
+                .map_err(Error::from)
+                .and_then(|body| {
+                    let res = if body.is_empty() {
+                        serde_json::from_value(Value::Null)
+                    } else {
+                        serde_json::from_slice(&body)
+                    };
+                    match res {
+                        Ok(params) => ok(Params(params)),
+                        Err(e) => err(RpcError::new(INVALID_PARAMS, "Invalid params")
+                            .data(Value::String(e.to_string()))
+                            .into()),
+                    }
+                }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::StatusCode;
+    use actix_http::test::TestRequest;
+    use actix_http::Request;
+    use actix_service::IntoNewService;
+
+    use super::*;
+    use crate::{App, Json};
+
+    fn add(params: Params<(i64, i64)>) -> Json<i64> {
+        Json((params.0).0 + (params.0).1)
+    }
+
+    fn concat(params: Params<(i64, i64)>) -> String {
+        format!("{}{}", (params.0).0, (params.0).1)
+    }
+
+    fn fail(_: Params<()>) -> Result<String, RpcError> {
+        Err(RpcError::new(1, "Failed").data(json!("reason")))
+    }
+
+    fn rpc_service(
+    ) -> impl Service<Request = Request, Response = ServiceResponse, Error = ()> {
+        App::new()
+            .config(|cfg: &mut JsonRpcConfig| {
+                cfg.limit(1024);
+            })
+            .service(
+                "/rpc",
+                JsonRpc::new()
+                    .method("add", add)
+                    .method("concat", concat)
+                    .method("fail", fail),
+            )
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap()
+    }
+
+    fn call(body: &str) -> (StatusCode, Bytes) {
+        let mut srv = rpc_service();
+        let req = TestRequest::with_uri("/rpc")
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from(body.to_owned()))
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        let body = match res.response().body() {
+            ResponseBody::Body(Body::Bytes(b)) | ResponseBody::Other(Body::Bytes(b)) => {
+                b.clone()
+            }
+            _ => Bytes::new(),
+        };
+        (res.status(), body)
+    }
+
+    fn call_json(body: &str) -> Value {
+        let (status, body) = call(body);
+        assert_eq!(status, StatusCode::OK);
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn test_dispatch() {
+        assert_eq!(
+            call_json(
+                r#"{"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1}"#
+            ),
+            json!({"jsonrpc": "2.0", "result": 3, "id": 1})
+        );
+        assert_eq!(
+            call_json(r#"{"jsonrpc": "2.0", "method": "fail", "id": "a"}"#),
+            json!({
+                "jsonrpc": "2.0",
+                "error": {"code": 1, "message": "Failed", "data": "reason"},
+                "id": "a"
+            })
+        );
+    }
+
+    #[test]
+    fn test_result_content_type() {
+        // non-json response is returned as a string
+        assert_eq!(
+            call_json(
+                r#"{"jsonrpc": "2.0", "method": "concat", "params": [1, 2], "id": 1}"#
+            ),
+            json!({"jsonrpc": "2.0", "result": "12", "id": 1})
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        let res = call_json(
+            r#"[
+                {"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1},
+                {"jsonrpc": "2.0", "method": "add", "params": [3, 4]},
+                {"jsonrpc": "2.0", "method": "add", "params": [5, 6], "id": 2}
+            ]"#,
+        );
+        assert_eq!(
+            res,
+            json!([
+                {"jsonrpc": "2.0", "result": 3, "id": 1},
+                {"jsonrpc": "2.0", "result": 11, "id": 2}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_notifications() {
+        let (status, body) =
+            call(r#"{"jsonrpc": "2.0", "method": "add", "params": [1, 2]}"#);
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(body.is_empty());
+
+        let (status, _) = call(
+            r#"[{"jsonrpc": "2.0", "method": "add", "params": [1, 2]},
+                {"jsonrpc": "2.0", "method": "unknown"}]"#,
+        );
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let res = call_json(r#"{"jsonrpc": "2.0", "method": "add""#);
+        assert_eq!(res["error"]["code"], PARSE_ERROR);
+        assert_eq!(res["id"], Value::Null);
+
+        let res = call_json("[]");
+        assert_eq!(res["error"]["code"], INVALID_REQUEST);
+
+        let res = call_json(r#"{"jsonrpc": "2.0", "params": [1], "id": 1}"#);
+        assert_eq!(res["error"]["code"], INVALID_REQUEST);
+        assert_eq!(res["id"], 1);
+
+        let res = call_json(r#"{"jsonrpc": "2.0", "method": "sub", "id": 2}"#);
+        assert_eq!(res["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(res["id"], 2);
+
+        let res = call_json(
+            r#"{"jsonrpc": "2.0", "method": "add", "params": ["a"], "id": 3}"#,
+        );
+        assert_eq!(res["error"]["code"], INVALID_PARAMS);
+        assert_eq!(res["id"], 3);
+    }
+
+    #[test]
+    fn test_limit() {
+        let params = vec!["1"; 1024].join(", ");
+        let (status, _) = call(&format!(
+            r#"{{"jsonrpc": "2.0", "method": "add", "params": [{}], "id": 1}}"#,
+            params
+        ));
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_parse_call() {
+        let (id, method, params) = parse_call(
+            json!({"jsonrpc": "2.0", "method": "add", "params": [1, 2], "id": 1}),
+        )
+        .unwrap();
+        assert_eq!(id, Some(json!(1)));
+        assert_eq!(method, "add");
+        assert_eq!(params, json!([1, 2]));
+
+        // notification
+        let (id, _, params) =
+            parse_call(json!({"jsonrpc": "2.0", "method": "ping"})).unwrap();
+        assert_eq!(id, None);
+        assert_eq!(params, Value::Null);
+
+        assert_eq!(
+            parse_call(json!({"jsonrpc": "1.0", "method": "add", "id": "a"})),
+            Err(json!("a"))
+        );
+        assert_eq!(
+            parse_call(json!({"jsonrpc": "2.0", "method": "add", "params": 1, "id": 1})),
+            Err(json!(1))
+        );
+        assert_eq!(parse_call(json!([1])), Err(Value::Null));
+    }
+
+    #[test]
+    fn test_rpc_error() {
+        let e = RpcError::new(INVALID_PARAMS, "Invalid params").data(json!("missing"));
+        let body = e.to_value().to_string();
+        assert_eq!(RpcError::from_slice(body.as_bytes()), Some(e));
+        assert_eq!(RpcError::from_slice(b"not found"), None);
+    }
+}
@@ -7,10 +7,23 @@ extern crate derive_more;
 extern crate lazy_static;
 
 mod app;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod binary;
+mod cached;
+#[cfg(feature = "cbor")]
+mod cbor;
 mod extractor;
 pub mod handler;
 mod helpers;
-// mod info;
+mod info;
+mod jsonrpc;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod ndjson;
+mod negotiate;
+mod params;
+mod query;
+mod redirect;
 pub mod filter;
 pub mod middleware;
 mod request;
@@ -18,25 +31,55 @@ mod resource;
 mod responder;
 mod route;
 mod service;
+mod sse;
 mod state;
 mod fs;
+mod validate;
 
 // re-export for convenience
 pub use actix_http::Response as HttpResponse;
 pub use actix_http::{http, Error, HttpMessage, ResponseError};
 
 pub use crate::app::App;
-pub use crate::extractor::{Form, Json, Path, Query};
+pub use crate::cached::Cached;
+#[cfg(feature = "cbor")]
+pub use crate::cbor::Cbor;
+pub use crate::extractor::{AnyBody, Form, Json, Path, Query};
 pub use crate::handler::FromRequest;
+pub use crate::jsonrpc::{JsonRpc, Params, RpcError};
+#[cfg(feature = "msgpack")]
+pub use crate::msgpack::MsgPack;
+pub use crate::ndjson::NdJson;
+pub use crate::negotiate::Negotiate;
+pub use crate::redirect::Redirect;
 pub use crate::request::HttpRequest;
 pub use crate::resource::Resource;
-pub use crate::responder::{Either, Responder};
+pub use crate::responder::{CustomResponder, Either, Responder, Streaming};
 pub use crate::service::{ServiceRequest, ServiceResponse};
+pub use crate::sse::{Event, LastEventId, Sse};
 pub use crate::state::State;
+pub use crate::validate::{Validate, Validated, ValidationErrors};
 
 pub mod dev {
     pub use crate::app::AppService;
+    #[cfg(any(feature = "msgpack", feature = "cbor"))]
+    pub use crate::binary::{BinaryConfig, BinaryPayloadError};
+    #[cfg(feature = "cbor")]
+    pub use crate::cbor::{CborConfig, CborPayloadError};
+    pub use crate::extractor::{
+        AnyBodyConfig, EitherExtractError, FormConfig, FormatDeserializer, JsonConfig,
+        PathConfig, PayloadConfig, QueryConfig,
+    };
     pub use crate::handler::{AsyncFactory, Extract, Factory, Handle};
+    pub use crate::info::{ConnectionInfo, ConnectionInfoConfig};
+    pub use crate::jsonrpc::{
+        JsonRpcConfig, JsonRpcService, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST,
+        METHOD_NOT_FOUND, PARSE_ERROR,
+    };
+    #[cfg(feature = "msgpack")]
+    pub use crate::msgpack::{MsgPackConfig, MsgPackPayloadError};
+    pub use crate::ndjson::NdJsonConfig;
+    pub use crate::negotiate::{Accept, NegotiateConfig};
     pub use crate::route::{Route, RouteBuilder};
-    // pub use crate::info::ConnectionInfo;
+    pub use crate::validate::{FieldError, ValidationConfig};
 }
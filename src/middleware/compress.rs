@@ -244,21 +244,43 @@ impl<B: MessageBody> Encoder<B> {
 
 pub(crate) struct Writer {
     buf: BytesMut,
+    limit: usize,
+    written: usize,
 }
 
 impl Writer {
-    fn new() -> Writer {
+    pub(crate) fn new() -> Writer {
+        Writer::with_limit(usize::max_value())
+    }
+    /// Writer that fails as soon as total written size exceeds the `limit`
+    pub(crate) fn with_limit(limit: usize) -> Writer {
         Writer {
             buf: BytesMut::with_capacity(8192),
+            limit,
+            written: 0,
         }
     }
-    fn take(&mut self) -> Bytes {
+    pub(crate) fn take(&mut self) -> Bytes {
         self.buf.take().freeze()
     }
+    pub(crate) fn len(&self) -> usize {
+        self.buf.len()
+    }
+    /// Total written size exceeded the limit
+    pub(crate) fn overflow(&self) -> bool {
+        self.written > self.limit
+    }
 }
 
 impl io::Write for Writer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written = self.written.saturating_add(buf.len());
+        if self.overflow() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "written data exceeds the limit",
+            ));
+        }
         self.buf.extend_from_slice(buf);
         Ok(buf.len())
     }
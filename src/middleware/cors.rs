@@ -0,0 +1,797 @@
+//! Cross-origin resource sharing (CORS) middleware
+use std::rc::Rc;
+
+use actix_http::body::ResponseBody;
+use actix_http::error::ResponseError;
+use actix_http::http::header::{self, HeaderName, HeaderValue};
+use actix_http::http::{HeaderMap, HttpTryFrom, Method, StatusCode};
+use actix_http::Response;
+use actix_service::{IntoNewTransform, Service, Transform};
+use derive_more::Display;
+use futures::future::ok;
+use futures::{Async, Future, Poll};
+use hashbrown::hash_set::HashSet;
+
+use crate::middleware::MiddlewareFactory;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
+
+/// A set of errors that can occur during processing CORS
+#[derive(Debug, Display, PartialEq)]
+pub enum CorsError {
+    /// The HTTP request header `Origin` could not be parsed correctly.
+    #[display(fmt = "The HTTP request header `Origin` could not be parsed correctly.")]
+    BadOrigin,
+    /// Origin is not allowed to make this request
+    #[display(fmt = "Origin is not allowed to make this request")]
+    OriginNotAllowed,
+    /// The request header `Access-Control-Request-Method` has an invalid value
+    #[display(
+        fmt = "The request header `Access-Control-Request-Method` has an invalid value"
+    )]
+    BadRequestMethod,
+    /// Requested method is not allowed
+    #[display(fmt = "Requested method is not allowed")]
+    MethodNotAllowed,
+    /// The request header `Access-Control-Request-Headers` has an invalid value
+    #[display(
+        fmt = "The request header `Access-Control-Request-Headers` has an invalid value"
+    )]
+    BadRequestHeaders,
+    /// One or more requested headers are not allowed
+    #[display(fmt = "One or more requested headers are not allowed")]
+    HeadersNotAllowed,
+}
+
+/// Return `BadRequest` for `CorsError`
+impl ResponseError for CorsError {
+    fn error_response(&self) -> Response {
+        Response::build(StatusCode::BAD_REQUEST).body(self.to_string())
+    }
+}
+
+/// `Middleware` for Cross-origin resource sharing support
+///
+/// Requests without `Origin` header are passed through unchanged, only
+/// `Vary: Origin` header is added if allowed origins are restricted.
+/// Preflight `OPTIONS` requests are answered by the middleware, actual
+/// requests are passed to the service and CORS headers are added to
+/// the response. Requests from origins that are not allowed and preflight
+/// requests with methods or headers that are not allowed are rejected
+/// with *400 Bad Request*.
+///
+/// By default any origin, any request header and `GET`, `HEAD`, `POST`,
+/// `PUT`, `PATCH`, `DELETE` and `OPTIONS` methods are allowed.
+///
+/// Middleware could be used for a whole application with
+/// `App::middleware()` or for a single resource with
+/// `Resource::middleware()`.
+///
+/// ```rust
+/// use actix_web2::middleware::Cors;
+/// use actix_web2::{http, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().resource("/users", |r| {
+///         r.get(|| HttpResponse::Ok().finish()).middleware(
+///             Cors::new()
+///                 .allowed_origin("https://www.rust-lang.org")
+///                 .allowed_methods(vec!["GET", "POST"])
+///                 .allowed_header(http::header::CONTENT_TYPE)
+///                 .expose_headers(vec!["x-request-id"])
+///                 .supports_credentials()
+///                 .max_age(3600),
+///         )
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Cors {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    origins: Option<HashSet<String>>,
+    origin_fns: Vec<Box<Fn(&str) -> bool>>,
+    methods: Vec<Method>,
+    headers: Option<HashSet<HeaderName>>,
+    expose_headers: Vec<HeaderName>,
+    max_age: Option<usize>,
+    credentials: bool,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Cors {
+            inner: Rc::new(Inner {
+                origins: None,
+                origin_fns: Vec::new(),
+                methods: vec![
+                    Method::GET,
+                    Method::HEAD,
+                    Method::POST,
+                    Method::PUT,
+                    Method::PATCH,
+                    Method::DELETE,
+                    Method::OPTIONS,
+                ],
+                headers: None,
+                expose_headers: Vec::new(),
+                max_age: None,
+                credentials: false,
+            }),
+        }
+    }
+}
+
+impl Cors {
+    /// Construct `Cors` middleware that allows any origin.
+    pub fn new() -> Cors {
+        Cors::default()
+    }
+
+    /// Add an origin that is allowed to make requests, i.e.
+    /// `https://www.rust-lang.org`.
+    ///
+    /// Origin is compared with the request's `Origin` header as is,
+    /// once any origin is added only listed origins are allowed.
+    pub fn allowed_origin(mut self, origin: &str) -> Self {
+        self.inner_mut()
+            .origins
+            .get_or_insert_with(HashSet::new)
+            .insert(origin.to_owned());
+        self
+    }
+
+    /// Add a list of origins that are allowed to make requests.
+    pub fn allowed_origins<U, O>(mut self, origins: U) -> Self
+    where
+        U: IntoIterator<Item = O>,
+        O: AsRef<str>,
+    {
+        for origin in origins {
+            self = self.allowed_origin(origin.as_ref());
+        }
+        self
+    }
+
+    /// Allow origins that match the predicate.
+    ///
+    /// Origin is allowed if it is one of the listed origins or
+    /// any of the predicates returns `true`.
+    pub fn allowed_origin_fn<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        self.inner_mut().origin_fns.push(Box::new(f));
+        self
+    }
+
+    /// Set a list of methods that are allowed for actual requests.
+    ///
+    /// Panics if method is not valid.
+    pub fn allowed_methods<U, M>(mut self, methods: U) -> Self
+    where
+        U: IntoIterator<Item = M>,
+        Method: HttpTryFrom<M>,
+    {
+        let mut allowed = Vec::new();
+        for method in methods {
+            #[allow(clippy::match_wild_err_arm)]
+            match Method::try_from(method) {
+                Ok(method) => allowed.push(method),
+                Err(_) => panic!("Can not create method"),
+            }
+        }
+        self.inner_mut().methods = allowed;
+        self
+    }
+
+    /// Add a header that is allowed for actual requests.
+    ///
+    /// Once any header is added only listed headers are allowed.
+    ///
+    /// Panics if header name is not valid.
+    pub fn allowed_header<H>(mut self, header: H) -> Self
+    where
+        HeaderName: HttpTryFrom<H>,
+    {
+        #[allow(clippy::match_wild_err_arm)]
+        match HeaderName::try_from(header) {
+            Ok(header) => {
+                self.inner_mut()
+                    .headers
+                    .get_or_insert_with(HashSet::new)
+                    .insert(header);
+            }
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    /// Add a list of headers that are allowed for actual requests.
+    ///
+    /// Panics if header name is not valid.
+    pub fn allowed_headers<U, H>(mut self, headers: U) -> Self
+    where
+        U: IntoIterator<Item = H>,
+        HeaderName: HttpTryFrom<H>,
+    {
+        for header in headers {
+            self = self.allowed_header(header);
+        }
+        self
+    }
+
+    /// Set a list of response headers that client is allowed to access.
+    ///
+    /// Panics if header name is not valid.
+    pub fn expose_headers<U, H>(mut self, headers: U) -> Self
+    where
+        U: IntoIterator<Item = H>,
+        HeaderName: HttpTryFrom<H>,
+    {
+        let mut expose = Vec::new();
+        for header in headers {
+            #[allow(clippy::match_wild_err_arm)]
+            match HeaderName::try_from(header) {
+                Ok(header) => expose.push(header),
+                Err(_) => panic!("Can not create header name"),
+            }
+        }
+        self.inner_mut().expose_headers = expose;
+        self
+    }
+
+    /// Set how long, in seconds, results of a preflight request
+    /// could be cached.
+    pub fn max_age(mut self, max_age: usize) -> Self {
+        self.inner_mut().max_age = Some(max_age);
+        self
+    }
+
+    /// Allow requests with credentials, i.e. cookies.
+    ///
+    /// Request's origin is always sent back instead of `*`, as browsers
+    /// reject wildcard origin for requests with credentials.
+    pub fn supports_credentials(mut self) -> Self {
+        self.inner_mut().credentials = true;
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
+}
+
+impl Inner {
+    /// Value of the `Access-Control-Allow-Origin` header for the origin
+    fn allow_origin(&self, origin: &HeaderValue) -> Result<HeaderValue, CorsError> {
+        let origin_str = origin.to_str().map_err(|_| CorsError::BadOrigin)?;
+
+        if self.origins.is_none() && self.origin_fns.is_empty() {
+            if self.credentials {
+                Ok(origin.clone())
+            } else {
+                Ok(HeaderValue::from_static("*"))
+            }
+        } else if self
+            .origins
+            .as_ref()
+            .map_or(false, |origins| origins.contains(origin_str))
+            || self.origin_fns.iter().any(|f| f(origin_str))
+        {
+            Ok(origin.clone())
+        } else {
+            Err(CorsError::OriginNotAllowed)
+        }
+    }
+
+    fn preflight(
+        &self,
+        req: &HttpRequest,
+        allow_origin: HeaderValue,
+    ) -> Result<Response, CorsError> {
+        let method = req
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|val| Method::from_bytes(val.as_bytes()).ok())
+            .ok_or(CorsError::BadRequestMethod)?;
+        if !self.methods.contains(&method) {
+            return Err(CorsError::MethodNotAllowed);
+        }
+
+        let requested = match req.headers().get(header::ACCESS_CONTROL_REQUEST_HEADERS) {
+            Some(val) => {
+                let val = val.to_str().map_err(|_| CorsError::BadRequestHeaders)?;
+                let mut headers = Vec::new();
+                for name in val.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                    let name = HeaderName::try_from(name)
+                        .map_err(|_| CorsError::BadRequestHeaders)?;
+                    if let Some(ref allowed) = self.headers {
+                        if !allowed.contains(&name) {
+                            return Err(CorsError::HeadersNotAllowed);
+                        }
+                    }
+                    headers.push(name);
+                }
+                headers
+            }
+            None => Vec::new(),
+        };
+
+        let mut res = Response::Ok();
+        res.header(header::ACCESS_CONTROL_ALLOW_METHODS, join(&self.methods));
+        match self.headers {
+            Some(ref headers) => {
+                let headers: Vec<_> = headers.iter().collect();
+                res.header(header::ACCESS_CONTROL_ALLOW_HEADERS, join(&headers));
+            }
+            None if !requested.is_empty() => {
+                res.header(header::ACCESS_CONTROL_ALLOW_HEADERS, join(&requested));
+            }
+            None => (),
+        }
+        if let Some(max_age) = self.max_age {
+            res.header(header::ACCESS_CONTROL_MAX_AGE, max_age.to_string());
+        }
+        let mut res = res.finish();
+        self.apply_headers(allow_origin, res.headers_mut());
+        Ok(res)
+    }
+
+    /// Check if response depends on the request's origin
+    fn varies_by_origin(&self) -> bool {
+        self.origins.is_some() || !self.origin_fns.is_empty() || self.credentials
+    }
+
+    /// Add `Vary: Origin` header, so caches do not mix up responses
+    /// for different origins
+    fn apply_vary(&self, headers: &mut HeaderMap) {
+        if self.varies_by_origin() {
+            headers.append(header::VARY, HeaderValue::from_static("origin"));
+        }
+    }
+
+    /// Add CORS headers to the response
+    fn apply_headers(&self, allow_origin: HeaderValue, headers: &mut HeaderMap) {
+        self.apply_vary(headers);
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+        if self.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+        if !self.expose_headers.is_empty() {
+            if let Ok(val) = HeaderValue::from_str(&join(&self.expose_headers)) {
+                headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, val);
+            }
+        }
+    }
+}
+
+fn join<T: AsRef<str>>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.as_ref())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl<S, P, B> IntoNewTransform<MiddlewareFactory<Cors, S>, S> for Cors
+where
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: 'static,
+{
+    fn into_new_transform(self) -> MiddlewareFactory<Cors, S> {
+        MiddlewareFactory::new(self)
+    }
+}
+
+impl<S, P, B> Transform<S> for Cors
+where
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest<P>;
+    type Response = ServiceResponse<B>;
+    type Error = S::Error;
+    type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(&mut self, req: ServiceRequest<P>, srv: &mut S) -> Self::Future {
+        // not a cross-origin request
+        let origin = match req.headers().get(header::ORIGIN) {
+            Some(origin) => origin.clone(),
+            None if self.inner.varies_by_origin() => {
+                let inner = self.inner.clone();
+                return Box::new(srv.call(req).map(move |mut res| {
+                    inner.apply_vary(res.headers_mut());
+                    res
+                }));
+            }
+            None => return Box::new(srv.call(req)),
+        };
+
+        let allow_origin = match self.inner.allow_origin(&origin) {
+            Ok(allow_origin) => allow_origin,
+            Err(e) => {
+                let mut res = e.error_response();
+                self.inner.apply_vary(res.headers_mut());
+                return Box::new(ok::<_, S::Error>(service_response(
+                    req.into_request(),
+                    res,
+                )));
+            }
+        };
+
+        if req.method() == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            let res = match self.inner.preflight(&req, allow_origin) {
+                Ok(res) => res,
+                Err(e) => e.error_response(),
+            };
+            return Box::new(ok::<_, S::Error>(service_response(
+                req.into_request(),
+                res,
+            )));
+        }
+
+        let inner = self.inner.clone();
+        Box::new(srv.call(req).map(move |mut res| {
+            inner.apply_headers(allow_origin, res.headers_mut());
+            res
+        }))
+    }
+}
+
+fn service_response<B>(req: HttpRequest, res: Response) -> ServiceResponse<B> {
+    ServiceResponse::new(
+        req,
+        res.map_body(|_, body| match body {
+            ResponseBody::Body(body) | ResponseBody::Other(body) => {
+                ResponseBody::Other(body)
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use actix_http::test::TestRequest;
+    use actix_http::{Extensions, Request};
+    use actix_router::{Path, Url};
+    use actix_service::{IntoNewService, NewService};
+
+    use super::*;
+    use crate::App;
+
+    fn request(req: Request) -> HttpRequest {
+        let (head, _) = req.into_parts();
+        let path = Path::new(Url::new(head.uri.clone()));
+        HttpRequest::new(head, path, Rc::new(Extensions::new()))
+    }
+
+    #[test]
+    fn test_allow_origin() {
+        let origin = HeaderValue::from_static("https://www.example.com");
+
+        let cors = Cors::new();
+        assert_eq!(cors.inner.allow_origin(&origin).unwrap(), "*");
+
+        let cors = Cors::new().supports_credentials();
+        assert_eq!(cors.inner.allow_origin(&origin).unwrap(), origin);
+
+        let cors = Cors::new().allowed_origins(vec!["https://www.rust-lang.org"]);
+        assert_eq!(
+            cors.inner.allow_origin(&origin),
+            Err(CorsError::OriginNotAllowed)
+        );
+
+        let cors = cors.allowed_origin_fn(|origin| origin.ends_with(".example.com"));
+        assert_eq!(cors.inner.allow_origin(&origin).unwrap(), origin);
+    }
+
+    #[test]
+    fn test_preflight() {
+        let cors = Cors::new()
+            .allowed_methods(vec!["GET", "POST"])
+            .allowed_header(header::CONTENT_TYPE)
+            .max_age(3600);
+        let origin = HeaderValue::from_static("*");
+
+        let req = request(
+            TestRequest::default()
+                .method(Method::OPTIONS)
+                .header(header::ORIGIN, "https://www.example.com")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "Content-Type")
+                .finish(),
+        );
+        let res = cors.inner.preflight(&req, origin.clone()).unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+                .unwrap(),
+            "GET, POST"
+        );
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+                .unwrap(),
+            "content-type"
+        );
+        assert_eq!(
+            res.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(),
+            "3600"
+        );
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "*"
+        );
+
+        let req = request(
+            TestRequest::default()
+                .method(Method::OPTIONS)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE")
+                .finish(),
+        );
+        assert_eq!(
+            cors.inner.preflight(&req, origin.clone()).unwrap_err(),
+            CorsError::MethodNotAllowed
+        );
+
+        let req = request(
+            TestRequest::default()
+                .method(Method::OPTIONS)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-custom")
+                .finish(),
+        );
+        assert_eq!(
+            cors.inner.preflight(&req, origin).unwrap_err(),
+            CorsError::HeadersNotAllowed
+        );
+    }
+
+    #[test]
+    fn test_apply_headers() {
+        let cors = Cors::new()
+            .allowed_origin("https://www.example.com")
+            .expose_headers(vec!["x-request-id", "x-version"])
+            .supports_credentials();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::VARY, HeaderValue::from_static("accept"));
+        cors.inner.apply_headers(
+            HeaderValue::from_static("https://www.example.com"),
+            &mut headers,
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://www.example.com"
+        );
+        assert_eq!(
+            headers
+                .get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS)
+                .unwrap(),
+            "true"
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_EXPOSE_HEADERS).unwrap(),
+            "x-request-id, x-version"
+        );
+        assert_eq!(headers.get_all(header::VARY).iter().count(), 2);
+
+        // wildcard origin does not depend on the request
+        let mut headers = HeaderMap::new();
+        Cors::new()
+            .inner
+            .apply_headers(HeaderValue::from_static("*"), &mut headers);
+        assert!(!headers.contains_key(header::VARY));
+    }
+
+    fn cors_service(
+        cors: Cors,
+        hits: Rc<Cell<usize>>,
+    ) -> impl Service<Request = Request, Response = ServiceResponse, Error = ()> {
+        App::new()
+            .resource("/test", move |r| {
+                r.to(move || {
+                    hits.set(hits.get() + 1);
+                    Response::Ok().header(header::VARY, "accept").finish()
+                })
+            })
+            .middleware(cors)
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_without_origin() {
+        let hits = Rc::new(Cell::new(0));
+        let mut srv = cors_service(Cors::new(), hits.clone());
+
+        let req = TestRequest::with_uri("/test").finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(hits.get(), 1);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers().get_all(header::VARY).iter().count(), 1);
+
+        // restricted origins, response depends on the origin
+        let cors = Cors::new().allowed_origin("https://www.example.com");
+        let mut srv = cors_service(cors, hits.clone());
+
+        let req = TestRequest::with_uri("/test").finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(hits.get(), 2);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        let vary: Vec<_> = res.headers().get_all(header::VARY).iter().collect();
+        assert_eq!(vary, vec!["accept", "origin"]);
+    }
+
+    #[test]
+    fn test_preflight_request() {
+        let hits = Rc::new(Cell::new(0));
+        let cors = Cors::new()
+            .allowed_origin("https://www.example.com")
+            .allowed_methods(vec!["GET", "POST"]);
+        let mut srv = cors_service(cors, hits.clone());
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(hits.get(), 0);
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "https://www.example.com"
+        );
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+                .unwrap(),
+            "GET, POST"
+        );
+
+        let req = TestRequest::with_uri("/test")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "DELETE")
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(hits.get(), 0);
+    }
+
+    #[test]
+    fn test_actual_request() {
+        let hits = Rc::new(Cell::new(0));
+        let cors = Cors::new()
+            .allowed_origin("https://www.example.com")
+            .expose_headers(vec!["x-request-id"]);
+        let mut srv = cors_service(cors, hits.clone());
+
+        let req = TestRequest::with_uri("/test")
+            .header(header::ORIGIN, "https://www.example.com")
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(hits.get(), 1);
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "https://www.example.com"
+        );
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+                .unwrap(),
+            "x-request-id"
+        );
+        let vary: Vec<_> = res.headers().get_all(header::VARY).iter().collect();
+        assert_eq!(vary, vec!["accept", "origin"]);
+    }
+
+    #[test]
+    fn test_origin_not_allowed() {
+        let hits = Rc::new(Cell::new(0));
+        let cors = Cors::new().allowed_origin("https://www.example.com");
+        let mut srv = cors_service(cors, hits.clone());
+
+        let req = TestRequest::with_uri("/test")
+            .header(header::ORIGIN, "https://www.rust-lang.org")
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(hits.get(), 0);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        assert_eq!(res.headers().get(header::VARY).unwrap(), "origin");
+    }
+
+    #[test]
+    fn test_resource_middleware() {
+        let mut srv = App::new()
+            .resource("/users", |r| {
+                r.get(|| Response::Ok().finish()).middleware(
+                    Cors::new()
+                        .allowed_origin("https://www.example.com")
+                        .allowed_methods(vec!["GET"])
+                        .max_age(3600),
+                )
+            })
+            .resource("/other", |r| r.get(|| Response::Ok().finish()))
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap();
+
+        // preflight is answered by the resource middleware, resource has
+        // no OPTIONS route
+        let req = TestRequest::with_uri("/users")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "https://www.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "https://www.example.com"
+        );
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+                .unwrap(),
+            "GET"
+        );
+        assert_eq!(
+            res.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(),
+            "3600"
+        );
+
+        // other resources are not affected
+        let req = TestRequest::with_uri("/other")
+            .header(header::ORIGIN, "https://www.example.com")
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
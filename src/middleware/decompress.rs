@@ -0,0 +1,468 @@
+//! Middleware for decompressing request payloads
+use std::io::{self, Write};
+
+use actix_http::error::PayloadError;
+use actix_http::http::header::{ContentEncoding, CONTENT_ENCODING, CONTENT_LENGTH};
+use actix_http::{HttpMessage, Payload, PayloadStream};
+use actix_service::{IntoNewTransform, Service, Transform};
+use bytes::Bytes;
+use futures::{Async, Poll, Stream};
+
+#[cfg(feature = "brotli")]
+use brotli2::write::BrotliDecoder;
+#[cfg(feature = "flate2")]
+use flate2::write::{GzDecoder, ZlibDecoder};
+
+use crate::middleware::compress::Writer;
+use crate::middleware::MiddlewareFactory;
+use crate::service::{ServiceRequest, ServiceResponse};
+
+/// `Middleware` for decompressing request payloads.
+///
+/// Payload of requests with `Content-Encoding: gzip`, `deflate` or `br`
+/// header is decoded before it reaches extractors, stacked encodings like
+/// `gzip, br` are decoded in reverse order. Requests with unknown encodings
+/// are passed through as is. `Content-Encoding` and `Content-Length`
+/// headers are removed from the decoded request unless request is already
+/// shared by an outer middleware. Size of the decoded payload is limited,
+/// decoding fails with `PayloadError::Overflow` error as soon as
+/// decompressed payload exceeds the limit. By default limit is 256Kb.
+///
+/// ```rust
+/// use actix_web2::{middleware, App, HttpResponse};
+/// use bytes::Bytes;
+///
+/// fn index(body: Bytes) -> HttpResponse {
+///     HttpResponse::Ok().body(body)
+/// }
+///
+/// fn main() {
+///     let app = App::new()
+///         .resource("/test", |r| r.post(index))
+///         .middleware(middleware::Decompress::new().limit(1_048_576));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Decompress {
+    limit: usize,
+}
+
+impl Default for Decompress {
+    fn default() -> Self {
+        Decompress { limit: 262_144 }
+    }
+}
+
+impl Decompress {
+    /// Construct `Decompress` middleware.
+    pub fn new() -> Decompress {
+        Decompress::default()
+    }
+
+    /// Change max size of decompressed payload. By default max size is 256Kb
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<S, B> IntoNewTransform<MiddlewareFactory<Decompress, S>, S> for Decompress
+where
+    S: Service<Request = ServiceRequest<PayloadStream>, Response = ServiceResponse<B>>,
+    S::Future: 'static,
+{
+    fn into_new_transform(self) -> MiddlewareFactory<Decompress, S> {
+        MiddlewareFactory::new(self)
+    }
+}
+
+impl<S, B> Transform<S> for Decompress
+where
+    S: Service<Request = ServiceRequest<PayloadStream>, Response = ServiceResponse<B>>,
+    S::Future: 'static,
+{
+    type Request = ServiceRequest<PayloadStream>;
+    type Response = ServiceResponse<B>;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(
+        &mut self,
+        mut req: ServiceRequest<PayloadStream>,
+        srv: &mut S,
+    ) -> S::Future {
+        let decoders = req
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|enc| ContentDecoder::decoders(enc, self.limit));
+
+        // unknown encodings are passed through as is
+        if let Some(decoders) = decoders {
+            // headers of a request that is already shared stay intact
+            if let Some(headers) = req.headers_mut() {
+                headers.remove(CONTENT_ENCODING);
+                headers.remove(CONTENT_LENGTH);
+            }
+
+            let mut stream: PayloadStream = Box::new(req.take_payload());
+            for decoder in decoders {
+                stream = Box::new(Decoder {
+                    stream,
+                    decoder: Some(decoder),
+                });
+            }
+            req.set_payload(Payload::Stream(stream));
+        }
+
+        srv.call(req)
+    }
+}
+
+/// Payload stream that decodes compressed chunks
+pub struct Decoder<S> {
+    stream: S,
+    decoder: Option<ContentDecoder>,
+}
+
+impl<S> Stream for Decoder<S>
+where
+    S: Stream<Item = Bytes, Error = PayloadError>,
+{
+    type Item = Bytes;
+    type Error = PayloadError;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, PayloadError> {
+        loop {
+            let decoder = match self.decoder {
+                Some(ref mut decoder) => decoder,
+                None => return Ok(Async::Ready(None)),
+            };
+
+            match self.stream.poll()? {
+                Async::NotReady => return Ok(Async::NotReady),
+                Async::Ready(Some(chunk)) => {
+                    if let Some(chunk) = decoder.feed_data(&chunk)? {
+                        return Ok(Async::Ready(Some(chunk)));
+                    }
+                }
+                Async::Ready(None) => {
+                    let chunk = decoder.feed_eof()?;
+                    self.decoder = None;
+                    return Ok(Async::Ready(chunk));
+                }
+            }
+        }
+    }
+}
+
+enum ContentDecoder {
+    #[cfg(feature = "flate2")]
+    Deflate(Box<ZlibDecoder<Writer>>),
+    #[cfg(feature = "flate2")]
+    Gzip(Box<GzDecoder<Writer>>),
+    #[cfg(feature = "brotli")]
+    Br(Box<BrotliDecoder<Writer>>),
+}
+
+impl ContentDecoder {
+    /// Decoders for a `Content-Encoding` header value in decoding order,
+    /// `None` if there is nothing to decode or some encoding is unknown
+    fn decoders(encodings: &str, limit: usize) -> Option<Vec<ContentDecoder>> {
+        let mut decoders = Vec::new();
+        // encodings are listed in the order they were applied
+        for encoding in encodings.rsplit(',').map(|enc| enc.trim()) {
+            if !encoding.eq_ignore_ascii_case("identity") {
+                decoders.push(ContentDecoder::decoder(encoding, limit)?);
+            }
+        }
+        if decoders.is_empty() {
+            None
+        } else {
+            Some(decoders)
+        }
+    }
+
+    fn decoder(encoding: &str, limit: usize) -> Option<Self> {
+        match ContentEncoding::from(encoding) {
+            #[cfg(feature = "flate2")]
+            ContentEncoding::Deflate => Some(ContentDecoder::Deflate(Box::new(
+                ZlibDecoder::new(Writer::with_limit(limit)),
+            ))),
+            #[cfg(feature = "flate2")]
+            ContentEncoding::Gzip => Some(ContentDecoder::Gzip(Box::new(
+                GzDecoder::new(Writer::with_limit(limit)),
+            ))),
+            #[cfg(feature = "brotli")]
+            ContentEncoding::Br => Some(ContentDecoder::Br(Box::new(
+                BrotliDecoder::new(Writer::with_limit(limit)),
+            ))),
+            _ => None,
+        }
+    }
+
+    fn writer(&mut self) -> &mut Writer {
+        match *self {
+            #[cfg(feature = "brotli")]
+            ContentDecoder::Br(ref mut decoder) => decoder.get_mut(),
+            #[cfg(feature = "flate2")]
+            ContentDecoder::Gzip(ref mut decoder) => decoder.get_mut(),
+            #[cfg(feature = "flate2")]
+            ContentDecoder::Deflate(ref mut decoder) => decoder.get_mut(),
+        }
+    }
+
+    /// Writer fails as soon as decoded data exceeds the limit
+    fn error(&mut self, err: io::Error) -> PayloadError {
+        if self.writer().overflow() {
+            PayloadError::Overflow
+        } else {
+            err.into()
+        }
+    }
+
+    /// Decode chunk, fails as soon as decoded data exceeds the limit
+    fn feed_data(&mut self, data: &[u8]) -> Result<Option<Bytes>, PayloadError> {
+        let res = match *self {
+            #[cfg(feature = "brotli")]
+            ContentDecoder::Br(ref mut decoder) => write_all(decoder.as_mut(), data),
+            #[cfg(feature = "flate2")]
+            ContentDecoder::Gzip(ref mut decoder) => write_all(decoder.as_mut(), data),
+            #[cfg(feature = "flate2")]
+            ContentDecoder::Deflate(ref mut decoder) => {
+                write_all(decoder.as_mut(), data)
+            }
+        };
+        if let Err(err) = res {
+            return Err(self.error(err));
+        }
+
+        let chunk = self.writer().take();
+        Ok(if chunk.is_empty() { None } else { Some(chunk) })
+    }
+
+    fn feed_eof(&mut self) -> Result<Option<Bytes>, PayloadError> {
+        let res = match *self {
+            #[cfg(feature = "brotli")]
+            ContentDecoder::Br(ref mut decoder) => decoder.finish().map(|_| ()),
+            #[cfg(feature = "flate2")]
+            ContentDecoder::Gzip(ref mut decoder) => decoder.try_finish(),
+            #[cfg(feature = "flate2")]
+            ContentDecoder::Deflate(ref mut decoder) => decoder.try_finish(),
+        };
+        if let Err(err) = res {
+            return Err(self.error(err));
+        }
+
+        let chunk = self.writer().take();
+        Ok(if chunk.is_empty() { None } else { Some(chunk) })
+    }
+}
+
+fn write_all<W: Write>(decoder: &mut W, data: &[u8]) -> io::Result<()> {
+    decoder.write_all(data)?;
+    decoder.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::body::{Body, ResponseBody};
+    use actix_http::http::Method;
+    use actix_http::test::TestRequest;
+    use actix_http::{Request, Response};
+    use actix_service::{IntoNewService, NewService};
+    use futures::future::{self, Future};
+    use futures::stream::once;
+
+    use super::*;
+    use crate::{App, HttpRequest};
+
+    #[cfg(feature = "flate2")]
+    fn gzip(data: &[u8]) -> Bytes {
+        let mut e =
+            flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        e.write_all(data).unwrap();
+        Bytes::from(e.finish().unwrap())
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_gzip_decoder() {
+        let data = "Hello World ".repeat(100);
+        let enc = gzip(data.as_ref());
+
+        let decoder = Decoder {
+            stream: once::<_, PayloadError>(Ok(enc.clone())),
+            decoder: ContentDecoder::decoder("gzip", 262_144),
+        };
+        let bytes = decoder.concat2().wait().unwrap();
+        assert_eq!(bytes, Bytes::from(data));
+
+        // decompressed payload is bigger than limit
+        let decoder = Decoder {
+            stream: once::<_, PayloadError>(Ok(enc)),
+            decoder: ContentDecoder::decoder("gzip", 100),
+        };
+        match decoder.concat2().wait() {
+            Err(PayloadError::Overflow) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_gzip_bomb() {
+        // 10Mb of zeros compress into a single small chunk
+        let enc = gzip(&vec![0u8; 10_485_760]);
+        assert!(enc.len() < 65_536);
+
+        let mut decoder = ContentDecoder::decoder("gzip", 262_144).unwrap();
+        match decoder.feed_data(&enc) {
+            Err(PayloadError::Overflow) => (),
+            _ => panic!(),
+        }
+        // nothing above the limit got buffered
+        assert!(decoder.writer().len() <= 262_144);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_decoders() {
+        assert!(ContentDecoder::decoders("identity", 1024).is_none());
+        assert!(ContentDecoder::decoders("gzip, unknown", 1024).is_none());
+        assert_eq!(
+            ContentDecoder::decoders("gzip, identity, deflate", 1024)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // gzip applied first, deflate second
+        let mut e =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        e.write_all(&gzip(b"Hello World")).unwrap();
+        let enc = Bytes::from(e.finish().unwrap());
+
+        let mut stream: PayloadStream = Box::new(once::<_, PayloadError>(Ok(enc)));
+        for decoder in ContentDecoder::decoders("gzip, deflate", 1024).unwrap() {
+            stream = Box::new(Decoder {
+                stream,
+                decoder: Some(decoder),
+            });
+        }
+        let bytes = stream.concat2().wait().unwrap();
+        assert_eq!(bytes, Bytes::from_static(b"Hello World"));
+    }
+
+    /// Middleware that keeps a clone of the request until response is ready
+    #[derive(Clone)]
+    struct Hold;
+
+    impl<S> IntoNewTransform<MiddlewareFactory<Hold, S>, S> for Hold
+    where
+        S: Service<Request = ServiceRequest<PayloadStream>, Response = ServiceResponse>,
+        S::Future: 'static,
+    {
+        fn into_new_transform(self) -> MiddlewareFactory<Hold, S> {
+            MiddlewareFactory::new(self)
+        }
+    }
+
+    impl<S> Transform<S> for Hold
+    where
+        S: Service<Request = ServiceRequest<PayloadStream>, Response = ServiceResponse>,
+        S::Future: 'static,
+    {
+        type Request = ServiceRequest<PayloadStream>;
+        type Response = ServiceResponse;
+        type Error = S::Error;
+        type Future = Box<Future<Item = ServiceResponse, Error = S::Error>>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(
+            &mut self,
+            req: ServiceRequest<PayloadStream>,
+            srv: &mut S,
+        ) -> Self::Future {
+            let held = req.request().clone();
+            Box::new(srv.call(req).and_then(move |res| {
+                drop(held);
+                future::ok(res)
+            }))
+        }
+    }
+
+    fn echo(req: HttpRequest, body: Bytes) -> Response {
+        let encoded = req.headers().contains_key(CONTENT_ENCODING);
+        Response::Ok()
+            .header("x-encoded", if encoded { "1" } else { "0" })
+            .body(body)
+    }
+
+    fn call<S>(srv: &mut S, req: Request) -> (String, Bytes)
+    where
+        S: Service<Request = Request, Response = ServiceResponse, Error = ()>,
+    {
+        let res = srv.call(req).wait().unwrap();
+        assert!(res.status().is_success());
+        let encoded = res.headers().get("x-encoded").unwrap().to_str().unwrap();
+        let body = match res.response().body() {
+            ResponseBody::Body(Body::Bytes(b)) | ResponseBody::Other(Body::Bytes(b)) => {
+                b.clone()
+            }
+            _ => Bytes::new(),
+        };
+        (encoded.to_owned(), body)
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_decompress() {
+        let mut srv = App::new()
+            .resource("/", |r| r.post(echo))
+            .middleware(Decompress::new())
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap();
+
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(gzip(b"Hello World"))
+            .finish();
+        let (encoded, body) = call(&mut srv, req);
+        assert_eq!(encoded, "0");
+        assert_eq!(body, Bytes::from_static(b"Hello World"));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_decompress_shared_request() {
+        let mut srv = App::new()
+            .resource("/", |r| r.post(echo))
+            .middleware(Decompress::new())
+            .middleware(Hold)
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap();
+
+        // payload is decoded, headers of the shared request stay intact
+        let req = TestRequest::default()
+            .method(Method::POST)
+            .header(CONTENT_ENCODING, "gzip")
+            .set_payload(gzip(b"Hello World"))
+            .finish();
+        let (encoded, body) = call(&mut srv, req);
+        assert_eq!(encoded, "1");
+        assert_eq!(body, Bytes::from_static(b"Hello World"));
+    }
+}
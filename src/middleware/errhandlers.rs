@@ -1,64 +1,110 @@
-use hashbrown::hash_map::HashMap;
 use std::rc::Rc;
-use futures::{Async, Future, Poll};
 
-use actix_http::error::Result;
-use actix_http::{Response, http::StatusCode};
+use actix_http::body::ResponseBody;
+use actix_http::error::{Error, Result};
+use actix_http::http::StatusCode;
+use actix_http::Response;
 use actix_service::{IntoNewTransform, Service, Transform};
+use futures::future::{ok, Either, FutureResult};
+use futures::{Async, Future, Poll};
+use hashbrown::hash_map::HashMap;
 
-use crate::service::ServiceRequest;
 use crate::middleware::MiddlewareFactory;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
 
-type ErrorHandler<P> = Fn(&ServiceRequest<P>, Response) -> Result<Response>;
+/// Error handler response
+pub enum ErrorHandlerResponse<B> {
+    /// New http response got generated
+    Response(ServiceResponse<B>),
+    /// Result is a future that resolves to a new http response
+    Future(Box<Future<Item = ServiceResponse<B>, Error = Error>>),
+}
+
+type ErrorHandler<B> = Fn(ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>>;
 
 /// `Middleware` for allowing custom handlers for responses.
 ///
-/// You can use `ErrorHandlers::handler()` method  to register a custom error
-/// handler for specific status code. You can modify existing response or
-/// create completely new one.
+/// You can use `ErrorHandlers::handler()` method to register a custom error
+/// handler for specific status code, `client_error()` and `server_error()`
+/// methods register handlers for all *4xx* and *5xx* responses. Handler of
+/// the specific status code takes precedence over the status class one.
+/// You can modify existing response or create completely new one.
 ///
 /// ## Example
 ///
 /// ```rust
-/// # extern crate actix_web;
-/// use actix_web::middleware::{ErrorHandlers, Response};
-/// use actix_web::{http, App, ServiceRequest, Response, Result};
+/// use actix_http::body::{Body, ResponseBody};
+/// use actix_http::error::Result;
+/// use actix_web2::middleware::{ErrorHandlerResponse, ErrorHandlers};
+/// use actix_web2::{http, App, HttpResponse, ServiceResponse};
 ///
-/// fn render_500<P>(_: &ServiceRequest<P>, resp: Response) -> Result<Response> {
-///     let mut builder = resp.into_builder();
-///     builder.header(http::header::CONTENT_TYPE, "application/json");
-///     Ok(Response::Done(builder.into()))
+/// fn render_500(mut res: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
+///     res.headers_mut().insert(
+///         http::header::CONTENT_TYPE,
+///         http::HeaderValue::from_static("text/html"),
+///     );
+///     // replace body with branded error page
+///     let res = res.map_body(|_, _| {
+///         ResponseBody::Other(Body::from("<h1>Something went wrong</h1>"))
+///     });
+///     Ok(ErrorHandlerResponse::Response(res))
 /// }
 ///
 /// fn main() {
 ///     let app = App::new()
-///         .middleware(
-///             ErrorHandlers::new()
-///                 .handler(http::StatusCode::INTERNAL_SERVER_ERROR, render_500),
-///         )
+///         .middleware(ErrorHandlers::new().server_error(render_500))
 ///         .resource("/test", |r| {
-///             r.method(http::Method::GET).f(|_| Response::Ok());
-///             r.method(http::Method::HEAD)
-///                 .f(|_| Response::MethodNotAllowed());
-///         })
-///         .finish();
+///             r.get(|| HttpResponse::Ok())
+///                 .head(|| HttpResponse::MethodNotAllowed())
+///         });
 /// }
 /// ```
+pub struct ErrorHandlers<B> {
+    inner: Rc<Inner<B>>,
+}
 
-#[derive(Clone)]
-pub struct ErrorHandlers<P> {
-    handlers: HashMap<StatusCode, Rc<ErrorHandler<P>>>,
+struct Inner<B> {
+    handlers: HashMap<StatusCode, Box<ErrorHandler<B>>>,
+    client_error: Option<Box<ErrorHandler<B>>>,
+    server_error: Option<Box<ErrorHandler<B>>>,
 }
 
-impl<P> Default for ErrorHandlers<P> {
+impl<B> Inner<B> {
+    fn get(&self, status: StatusCode) -> Option<&ErrorHandler<B>> {
+        if let Some(handler) = self.handlers.get(&status) {
+            Some(handler.as_ref())
+        } else if status.is_client_error() {
+            self.client_error.as_ref().map(|h| h.as_ref())
+        } else if status.is_server_error() {
+            self.server_error.as_ref().map(|h| h.as_ref())
+        } else {
+            None
+        }
+    }
+}
+
+impl<B> Default for ErrorHandlers<B> {
     fn default() -> Self {
         ErrorHandlers {
-            handlers: HashMap::new(),
+            inner: Rc::new(Inner {
+                handlers: HashMap::new(),
+                client_error: None,
+                server_error: None,
+            }),
+        }
+    }
+}
+
+impl<B> Clone for ErrorHandlers<B> {
+    fn clone(&self) -> Self {
+        ErrorHandlers {
+            inner: self.inner.clone(),
         }
     }
 }
 
-impl<P> ErrorHandlers<P> {
+impl<B> ErrorHandlers<B> {
     /// Construct new `ErrorHandlers` instance
     pub fn new() -> Self {
         ErrorHandlers::default()
@@ -67,31 +113,57 @@ impl<P> ErrorHandlers<P> {
     /// Register error handler for specified status code
     pub fn handler<F>(mut self, status: StatusCode, handler: F) -> Self
     where
-        F: Fn(&ServiceRequest<P>, Response) -> Result<Response> + 'static,
+        F: Fn(ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> + 'static,
+    {
+        self.inner_mut().handlers.insert(status, Box::new(handler));
+        self
+    }
+
+    /// Register error handler for all *4xx* responses
+    pub fn client_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> + 'static,
     {
-        self.handlers.insert(status, Rc::new(handler));
+        self.inner_mut().client_error = Some(Box::new(handler));
         self
     }
+
+    /// Register error handler for all *5xx* responses
+    pub fn server_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> + 'static,
+    {
+        self.inner_mut().server_error = Some(Box::new(handler));
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner<B> {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
 }
 
-impl<S, P: Clone> IntoNewTransform<MiddlewareFactory<ErrorHandlers<P>, S>, S>
-    for ErrorHandlers<P>
+impl<S, P, B> IntoNewTransform<MiddlewareFactory<ErrorHandlers<B>, S>, S>
+    for ErrorHandlers<B>
 where
-    S: Service<Request = ServiceRequest<P>, Response = Response>,
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
     S::Future: 'static,
+    S::Error: 'static,
+    B: 'static,
 {
-    fn into_new_transform(self) -> MiddlewareFactory<ErrorHandlers<P>, S> {
+    fn into_new_transform(self) -> MiddlewareFactory<ErrorHandlers<B>, S> {
         MiddlewareFactory::new(self)
     }
 }
 
-impl<S, P> Transform<S> for ErrorHandlers<P>
+impl<S, P, B> Transform<S> for ErrorHandlers<B>
 where
-    S: Service<Request = ServiceRequest<P>, Response = Response>,
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
     S::Future: 'static,
+    S::Error: 'static,
+    B: 'static,
 {
     type Request = ServiceRequest<P>;
-    type Response = Response;
+    type Response = ServiceResponse<B>;
     type Error = S::Error;
     type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
 
@@ -100,84 +172,168 @@ where
     }
 
     fn call(&mut self, req: ServiceRequest<P>, srv: &mut S) -> Self::Future {
-        let x = srv.call(req).map(move |mut resp| {
-            if let Some(handler) = self.handlers.get(&resp.status()) {
-                handler(&req, resp)
-            } else {
-                Ok(resp)
+        let inner = self.inner.clone();
+
+        Box::new(srv.call(req).and_then(move |res| {
+            let handler = match inner.get(res.status()) {
+                Some(handler) => handler,
+                None => return Either::A(ok(res)),
+            };
+
+            // handler errors are converted to responses
+            let req = res.request().clone();
+            match handler(res) {
+                Ok(ErrorHandlerResponse::Response(res)) => Either::A(ok(res)),
+                Ok(ErrorHandlerResponse::Future(fut)) => {
+                    Either::B(fut.then(move |res| -> FutureResult<_, S::Error> {
+                        match res {
+                            Ok(res) => ok(res),
+                            Err(e) => ok(error_response(req, e)),
+                        }
+                    }))
+                }
+                Err(e) => Either::A(ok(error_response(req, e))),
             }
-        });
-        unimplemented!()
+        }))
     }
 }
 
-// impl<S: 'static> Middleware<P> for ErrorHandlers<P> {
-//     fn response(&self, req: &ServiceRequest<P>, resp: Response) -> Result<Response> {
-//         if let Some(handler) = self.handlers.get(&resp.status()) {
-//             handler(req, resp)
-//         } else {
-//             Ok(Response::Done(resp))
-//         }
-//     }
-// }
+fn error_response<B>(req: HttpRequest, e: Error) -> ServiceResponse<B> {
+    let res: Response = e.into();
+    ServiceResponse::new(
+        req,
+        res.map_body(|_, body| match body {
+            ResponseBody::Body(body) | ResponseBody::Other(body) => {
+                ResponseBody::Other(body)
+            }
+        }),
+    )
+}
 
 #[cfg(test)]
 mod tests {
+    use actix_http::body::Body;
+    use actix_http::error::ErrorBadRequest;
+    use actix_http::http::header::CONTENT_TYPE;
+    use actix_http::test::TestRequest;
+    use actix_http::Request;
+    use actix_service::{IntoNewService, NewService};
+    use futures::future::err;
+
     use super::*;
-    use error::{Error, ErrorInternalServerError};
-    use http::header::CONTENT_TYPE;
-    use http::StatusCode;
-    use httpmessage::HttpMessage;
-    use middleware::Started;
-    use test::{self, TestRequest};
+    use crate::App;
 
-    fn render_500<P>(_: &ServiceRequest<P>, resp: Response) -> Result<Response> {
-        let mut builder = resp.into_builder();
-        builder.header(CONTENT_TYPE, "0001");
-        Ok(Response::Done(builder.into()))
+    fn handler(res: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
+        Ok(ErrorHandlerResponse::Response(res))
+    }
+
+    #[test]
+    fn test_handler_lookup() {
+        let mw = ErrorHandlers::new()
+            .handler(StatusCode::NOT_FOUND, handler)
+            .server_error(handler);
+
+        assert!(mw.inner.get(StatusCode::NOT_FOUND).is_some());
+        assert!(mw.inner.get(StatusCode::BAD_REQUEST).is_none());
+        assert!(mw.inner.get(StatusCode::BAD_GATEWAY).is_some());
+        assert!(mw.inner.get(StatusCode::OK).is_none());
+
+        let mw = ErrorHandlers::new().client_error(handler);
+        assert!(mw.inner.get(StatusCode::BAD_REQUEST).is_some());
+        assert!(mw.inner.get(StatusCode::INTERNAL_SERVER_ERROR).is_none());
+    }
+
+    fn render_500(mut res: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
+        res.headers_mut()
+            .insert(CONTENT_TYPE, "0001".parse().unwrap());
+        Ok(ErrorHandlerResponse::Response(res))
+    }
+
+    fn render_404(mut res: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
+        res.headers_mut()
+            .insert(CONTENT_TYPE, "0002".parse().unwrap());
+        Ok(ErrorHandlerResponse::Future(Box::new(ok(res))))
+    }
+
+    fn render_503(_: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
+        Ok(ErrorHandlerResponse::Future(Box::new(err(
+            ErrorBadRequest("future error"),
+        ))))
+    }
+
+    fn fail(_: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
+        Err(ErrorBadRequest("handler error"))
+    }
+
+    fn service(
+    ) -> impl Service<Request = Request, Response = ServiceResponse, Error = ()> {
+        App::new()
+            .resource("/ok", |r| r.to(|| Response::Ok().finish()))
+            .resource("/500", |r| {
+                r.to(|| Response::InternalServerError().finish())
+            })
+            .resource("/503", |r| r.to(|| Response::ServiceUnavailable().finish()))
+            .resource("/404", |r| r.to(|| Response::NotFound().finish()))
+            .resource("/502", |r| r.to(|| Response::BadGateway().finish()))
+            .middleware(
+                ErrorHandlers::new()
+                    .handler(StatusCode::INTERNAL_SERVER_ERROR, render_500)
+                    .handler(StatusCode::SERVICE_UNAVAILABLE, render_503)
+                    .handler(StatusCode::BAD_GATEWAY, fail)
+                    .client_error(render_404),
+            )
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap()
     }
 
     #[test]
     fn test_handler() {
-        let mw =
-            ErrorHandlers::new().handler(StatusCode::INTERNAL_SERVER_ERROR, render_500);
-
-        let mut req = TestRequest::default().finish();
-        let resp = Response::InternalServerError().finish();
-        let resp = match mw.response(&mut req, resp) {
-            Ok(Response::Done(resp)) => resp,
-            _ => panic!(),
-        };
-        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0001");
-
-        let resp = Response::Ok().finish();
-        let resp = match mw.response(&mut req, resp) {
-            Ok(Response::Done(resp)) => resp,
-            _ => panic!(),
-        };
-        assert!(!resp.headers().contains_key(CONTENT_TYPE));
-    }
-
-    struct MiddlewareOne;
-
-    impl<P> Middleware<P> for MiddlewareOne {
-        fn start(&self, _: &ServiceRequest<P>) -> Result<Started, Error> {
-            Err(ErrorInternalServerError("middleware error"))
-        }
+        let mut srv = service();
+
+        let res = srv
+            .call(TestRequest::with_uri("/500").finish())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "0001");
+
+        let res = srv
+            .call(TestRequest::with_uri("/ok").finish())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!res.headers().contains_key(CONTENT_TYPE));
     }
 
     #[test]
-    fn test_middleware_start_error() {
-        let mut srv = test::TestServer::new(move |app| {
-            app.middleware(
-                ErrorHandlers::new()
-                    .handler(StatusCode::INTERNAL_SERVER_ERROR, render_500),
-            ).middleware(MiddlewareOne)
-            .handler(|_| Response::Ok())
-        });
-
-        let request = srv.get().finish().unwrap();
-        let response = srv.execute(request.send()).unwrap();
-        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "0001");
+    fn test_handler_future() {
+        let mut srv = service();
+
+        let res = srv
+            .call(TestRequest::with_uri("/404").finish())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "0002");
+
+        // future errors are converted to responses
+        let res = srv
+            .call(TestRequest::with_uri("/503").finish())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_handler_error() {
+        let mut srv = service();
+
+        let res = srv
+            .call(TestRequest::with_uri("/502").finish())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
     }
 }
@@ -1,19 +1,31 @@
 //! Request logging middleware
-use hashbrown::hash_set::HashSet;
-use std::env;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fmt::{self, Display, Formatter};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
+use std::{env, io};
 
+use actix_http::body::{BodyLength, MessageBody, ResponseBody};
+use actix_http::error::Error;
+use actix_http::http::{HeaderName, HttpTryFrom, Method, StatusCode};
+use actix_http::ResponseHead;
+use actix_service::{IntoNewTransform, Service, Transform};
+use bytes::Bytes;
+use futures::sync::mpsc::UnboundedSender;
 use futures::{Async, Future, Poll};
+use hashbrown::hash_set::HashSet;
+use log::{info, trace, warn};
 use regex::Regex;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json;
 use time;
-use log::{trace, info};
-
-use actix_http::error::Result;
-use actix_http::{Response};
-use actix_service::{IntoNewTransform, Service, Transform};
 
-use crate::service::ServiceRequest;
+use crate::info::ConnectionInfo;
 use crate::middleware::MiddlewareFactory;
+use crate::request::HttpRequest;
+use crate::service::{ServiceRequest, ServiceResponse};
 
 /// `Middleware` for logging request and response info to the terminal.
 ///
@@ -31,19 +43,16 @@ use crate::middleware::MiddlewareFactory;
 ///  %a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T
 /// ```
 /// ```rust
-/// # extern crate actix_web;
-/// extern crate env_logger;
-/// use actix_web::middleware::Logger;
-/// use actix_web::App;
+/// use actix_web2::middleware::Logger;
+/// use actix_web2::App;
 ///
 /// fn main() {
-///     std::env::set_var("RUST_LOG", "actix_web=info");
+///     std::env::set_var("RUST_LOG", "actix_web2=info");
 ///     env_logger::init();
 ///
 ///     let app = App::new()
 ///         .middleware(Logger::default())
-///         .middleware(Logger::new("%a %{User-Agent}i"))
-///         .finish();
+///         .middleware(Logger::new("%a %{User-Agent}i"));
 /// }
 /// ```
 ///
@@ -51,7 +60,9 @@ use crate::middleware::MiddlewareFactory;
 ///
 /// `%%`  The percent sign
 ///
-/// `%a`  Remote IP-address (IP-address of proxy if using reverse proxy)
+/// `%a`  Remote IP-address, forwarded address is used only if the request
+/// came from a trusted proxy, see
+/// [**ConnectionInfoConfig**](../dev/struct.ConnectionInfoConfig.html)
 ///
 /// `%t`  Time when the request was started to process
 ///
@@ -59,7 +70,7 @@ use crate::middleware::MiddlewareFactory;
 ///
 /// `%s`  Response status code
 ///
-/// `%b`  Size of response in bytes, including HTTP headers
+/// `%b`  Size of response body in bytes, as sent to the client
 ///
 /// `%T` Time taken to serve the request, in seconds with floating fraction in
 /// .06f format
@@ -72,26 +83,167 @@ use crate::middleware::MiddlewareFactory;
 ///
 /// `%{FOO}e`  os.environ['FOO']
 ///
-#[derive(Clone)]
+/// `%{FOO}xi`  custom request value, see `Logger::custom_request_replace()`
+///
+/// `%{FOO}xo`  custom response value, see `Logger::custom_response_replace()`
+///
+/// ```rust
+/// use actix_web2::middleware::Logger;
+/// use actix_web2::App;
+///
+/// struct UserId(String);
+///
+/// fn main() {
+///     let logger = Logger::new("%r %s %{user}xi %{cache}xo")
+///         .custom_request_replace("user", |req| {
+///             req.extensions()
+///                 .get::<UserId>()
+///                 .map(|id| id.0.clone())
+///                 .unwrap_or_else(|| "-".to_owned())
+///         })
+///         .custom_response_replace("cache", |_, res| {
+///             res.headers
+///                 .get("x-cache")
+///                 .and_then(|v| v.to_str().ok())
+///                 .unwrap_or("MISS")
+///                 .to_owned()
+///         });
+///     let app = App::new().middleware(logger);
+/// }
+/// ```
+///
+/// ## Structured logging
+///
+/// `Logger` could also write [**AccessLog**](struct.AccessLog.html)
+/// records to an [**AccessLogSink**](trait.AccessLogSink.html), see
+/// `Logger::sink()` and `Logger::structured()`.
 pub struct Logger {
-    format: Format,
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    format: Option<Format>,
     exclude: HashSet<String>,
+    sink: Option<Rc<AccessLogSink>>,
+    headers: Vec<HeaderName>,
 }
 
 impl Logger {
     /// Create `Logger` middleware with the specified `format`.
     pub fn new(format: &str) -> Logger {
         Logger {
-            format: Format::new(format),
-            exclude: HashSet::new(),
+            inner: Rc::new(Inner {
+                format: Some(Format::new(format)),
+                exclude: HashSet::new(),
+                sink: None,
+                headers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Create `Logger` middleware that writes structured access log
+    /// records to the `sink` only, nothing is logged with `log` crate.
+    pub fn structured<T: AccessLogSink + 'static>(sink: T) -> Logger {
+        Logger {
+            inner: Rc::new(Inner {
+                format: None,
+                exclude: HashSet::new(),
+                sink: Some(Rc::new(sink)),
+                headers: Vec::new(),
+            }),
         }
     }
 
     /// Ignore and do not log access info for specified path.
     pub fn exclude<T: Into<String>>(mut self, path: T) -> Self {
-        self.exclude.insert(path.into());
+        self.inner_mut().exclude.insert(path.into());
         self
     }
+
+    /// Register a function that renders `%{label}xi` placeholder.
+    ///
+    /// Function is called after the request is handled, so it could read
+    /// values that handler or inner middlewares store in the request
+    /// extensions. At that point `ServiceRequest` is already consumed by
+    /// the service, so function receives `HttpRequest` of the response.
+    /// Unregistered placeholders are rendered as `-`.
+    ///
+    /// Panics if logger is created with `Logger::structured()`, it has no
+    /// log format to render placeholders in.
+    pub fn custom_request_replace<F>(mut self, label: &str, f: F) -> Self
+    where
+        F: Fn(&HttpRequest) -> String + 'static,
+    {
+        assert!(
+            self.inner.format.is_some(),
+            "Custom placeholder for a structured logger"
+        );
+        let f = CustomRequestFn(Rc::new(f));
+        if let Some(ref mut format) = self.inner_mut().format {
+            for unit in &mut format.0 {
+                if let FormatText::CustomRequest(ref name, ref mut func) = *unit {
+                    if name == label {
+                        *func = Some(f.clone());
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Register a function that renders `%{label}xo` placeholder.
+    ///
+    /// Function receives the request and head of the response instead of
+    /// `ServiceResponse`, so it does not depend on the response body type.
+    /// Unregistered placeholders are rendered as `-`.
+    ///
+    /// Panics if logger is created with `Logger::structured()`.
+    pub fn custom_response_replace<F>(mut self, label: &str, f: F) -> Self
+    where
+        F: Fn(&HttpRequest, &ResponseHead) -> String + 'static,
+    {
+        assert!(
+            self.inner.format.is_some(),
+            "Custom placeholder for a structured logger"
+        );
+        let f = CustomResponseFn(Rc::new(f));
+        if let Some(ref mut format) = self.inner_mut().format {
+            for unit in &mut format.0 {
+                if let FormatText::CustomResponse(ref name, ref mut func) = *unit {
+                    if name == label {
+                        *func = Some(f.clone());
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Write structured access log records to the `sink`, in addition
+    /// to the formatted log line.
+    pub fn sink<T: AccessLogSink + 'static>(mut self, sink: T) -> Self {
+        self.inner_mut().sink = Some(Rc::new(sink));
+        self
+    }
+
+    /// Include request header into structured access log records.
+    ///
+    /// Panics if header name is not valid.
+    pub fn header<K>(mut self, name: K) -> Self
+    where
+        HeaderName: HttpTryFrom<K>,
+    {
+        #[allow(clippy::match_wild_err_arm)]
+        match HeaderName::try_from(name) {
+            Ok(name) => self.inner_mut().headers.push(name),
+            Err(_) => panic!("Can not create header name"),
+        }
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Rc::get_mut(&mut self.inner).expect("Multiple copies exist")
+    }
 }
 
 impl Default for Logger {
@@ -102,31 +254,28 @@ impl Default for Logger {
     /// ```
     fn default() -> Logger {
         Logger {
-            format: Format::default(),
-            exclude: HashSet::new(),
+            inner: Rc::new(Inner {
+                format: Some(Format::default()),
+                exclude: HashSet::new(),
+                sink: None,
+                headers: Vec::new(),
+            }),
         }
     }
 }
 
-struct StartTime(time::Tm);
-
-impl Logger {
-    fn log<S>(&self, req: &ServiceRequest<S>, resp: &Response) {
-        if let Some(entry_time) = req.extensions().get::<StartTime>() {
-            let render = |fmt: &mut Formatter| {
-                for unit in &self.format.0 {
-                    unit.render(fmt, req, resp, entry_time.0)?;
-                }
-                Ok(())
-            };
-            info!("{}", FormatDisplay(&render));
+impl Clone for Logger {
+    fn clone(&self) -> Self {
+        Logger {
+            inner: self.inner.clone(),
         }
     }
 }
 
-impl<S, P> IntoNewTransform<MiddlewareFactory<Logger, S>, S> for Logger
+impl<S, P, B> IntoNewTransform<MiddlewareFactory<Logger, S>, S> for Logger
 where
-    S: Service<Request = ServiceRequest<P>, Response = Response>,
+    B: MessageBody,
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
     S::Future: 'static,
 {
     fn into_new_transform(self) -> MiddlewareFactory<Logger, S> {
@@ -134,44 +283,345 @@ where
     }
 }
 
-impl<S, P> Transform<S> for Logger
+impl<S, P, B> Transform<S> for Logger
 where
-    S: Service<Request = ServiceRequest<P>, Response = Response>,
+    B: MessageBody,
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
     S::Future: 'static,
 {
     type Request = ServiceRequest<P>;
-    type Response = Response;
+    type Response = ServiceResponse<StreamLog<B>>;
     type Error = S::Error;
-    type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
+    type Future = LoggerResponse<S, P, B>;
 
     fn poll_ready(&mut self) -> Poll<(), Self::Error> {
         Ok(Async::Ready(()))
     }
 
     fn call(&mut self, req: ServiceRequest<P>, srv: &mut S) -> Self::Future {
-        if !self.exclude.contains(req.path()) {
-            req.extensions_mut().insert(StartTime(time::now()));
+        let time = time::now();
+
+        if self.inner.exclude.contains(req.path()) {
+            return LoggerResponse {
+                format: None,
+                access: None,
+                time,
+                fut: srv.call(req),
+            };
         }
-        let x = srv.call(req).map(move |mut resp| {
+
+        // request parts are rendered upfront, request is moved to the service
+        let format = self.inner.format.as_ref().map(|format| {
+            let mut format = format.clone();
+            for unit in &mut format.0 {
+                unit.render_request(time, &req);
+            }
+            format
         });
+        let access = self.inner.sink.as_ref().map(|sink| {
+            (
+                sink.clone(),
+                AccessLog::new(time, &req, &self.inner.headers),
+            )
+        });
+
+        LoggerResponse {
+            format,
+            access,
+            time,
+            fut: srv.call(req),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct LoggerResponse<S, P, B>
+where
+    B: MessageBody,
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
+{
+    fut: S::Future,
+    time: time::Tm,
+    format: Option<Format>,
+    access: Option<(Rc<AccessLogSink>, AccessLog)>,
+}
+
+impl<S, P, B> Future for LoggerResponse<S, P, B>
+where
+    B: MessageBody,
+    S: Service<Request = ServiceRequest<P>, Response = ServiceResponse<B>>,
+{
+    type Item = ServiceResponse<StreamLog<B>>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let res = futures::try_ready!(self.fut.poll());
+
+        let mut format = self.format.take();
+        if let Some(ref mut format) = format {
+            for unit in &mut format.0 {
+                unit.render_response(&res);
+            }
+        }
+        let mut access = self.access.take();
+        if let Some((_, ref mut record)) = access {
+            record.status = res.response().status();
+        }
+
+        let time = self.time;
+        Ok(Async::Ready(res.map_body(move |_, body| {
+            ResponseBody::Body(StreamLog {
+                body,
+                format,
+                access,
+                time,
+                size: 0,
+            })
+        })))
+    }
+}
+
+/// Response body that counts streamed bytes, access log entry is
+/// written once the body is dropped.
+#[doc(hidden)]
+pub struct StreamLog<B> {
+    body: ResponseBody<B>,
+    format: Option<Format>,
+    access: Option<(Rc<AccessLogSink>, AccessLog)>,
+    size: usize,
+    time: time::Tm,
+}
+
+impl<B> Drop for StreamLog<B> {
+    fn drop(&mut self) {
+        if let Some(ref format) = self.format {
+            let render = |fmt: &mut Formatter| {
+                for unit in &format.0 {
+                    unit.render(fmt, self.size, self.time)?;
+                }
+                Ok(())
+            };
+            info!("{}", FormatDisplay(&render));
+        }
+        if let Some((sink, mut record)) = self.access.take() {
+            record.bytes = self.size;
+            record.duration = (time::now() - self.time).to_std().unwrap_or_default();
+            sink.write(record);
+        }
+    }
+}
+
+impl<B: MessageBody> MessageBody for StreamLog<B> {
+    fn length(&self) -> BodyLength {
+        self.body.length()
+    }
+
+    fn poll_next(&mut self) -> Poll<Option<Bytes>, Error> {
+        match self.body.poll_next()? {
+            Async::Ready(Some(chunk)) => {
+                self.size += chunk.len();
+                Ok(Async::Ready(Some(chunk)))
+            }
+            val => Ok(val),
+        }
+    }
+}
+
+/// Structured access log record
+///
+/// Record is serialized to a map with `time` (rfc3339), `method`, `path`,
+/// `status`, `bytes`, `duration` (in seconds), `remote_addr` and `headers`
+/// keys.
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+    time: time::Tm,
+    method: Method,
+    path: String,
+    status: StatusCode,
+    bytes: usize,
+    duration: Duration,
+    remote_addr: Option<String>,
+    headers: Vec<(HeaderName, String)>,
+}
+
+impl AccessLog {
+    fn new(time: time::Tm, req: &HttpRequest, names: &[HeaderName]) -> AccessLog {
+        let headers = names
+            .iter()
+            .filter_map(|name| {
+                let val = req.headers().get(name)?.to_str().ok()?;
+                Some((name.clone(), val.to_owned()))
+            })
+            .collect();
+
+        AccessLog {
+            time,
+            headers,
+            method: req.method().clone(),
+            path: req.path().to_owned(),
+            status: StatusCode::OK,
+            bytes: 0,
+            duration: Duration::default(),
+            remote_addr: ConnectionInfo::get(req).remote().map(|s| s.to_owned()),
+        }
+    }
+
+    /// Time when the request was started to process
+    pub fn time(&self) -> &time::Tm {
+        &self.time
+    }
+
+    /// Request method
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// Request path, without query string
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Response status code
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Size of response body in bytes, as sent to the client
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Time taken to serve the request
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Remote address, see `ConnectionInfo::remote()`
+    pub fn remote_addr(&self) -> Option<&str> {
+        self.remote_addr.as_ref().map(|s| s.as_str())
+    }
+
+    /// Value of the request header selected with `Logger::header()`
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, val)| val.as_str())
+    }
+}
+
+impl Serialize for AccessLog {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let duration = self.duration.as_secs() as f64
+            + f64::from(self.duration.subsec_nanos()) / 1_000_000_000.0;
+        let headers: BTreeMap<_, _> = self
+            .headers
+            .iter()
+            .map(|(name, val)| (name.as_str(), val.as_str()))
+            .collect();
+
+        let mut s = serializer.serialize_struct("AccessLog", 8)?;
+        s.serialize_field("time", &self.time.rfc3339().to_string())?;
+        s.serialize_field("method", self.method.as_str())?;
+        s.serialize_field("path", &self.path)?;
+        s.serialize_field("status", &self.status.as_u16())?;
+        s.serialize_field("bytes", &self.bytes)?;
+        s.serialize_field("duration", &duration)?;
+        s.serialize_field("remote_addr", &self.remote_addr)?;
+        s.serialize_field("headers", &headers)?;
+        s.end()
+    }
+}
+
+/// Destination of the structured access log records
+///
+/// Implemented for closures, `std::sync::mpsc::Sender` and
+/// `futures::sync::mpsc::UnboundedSender` channels, use
+/// [**AccessLogWriter**](struct.AccessLogWriter.html) to write records
+/// to a file.
+///
+/// ```rust
+/// use actix_web2::middleware::{AccessLog, Logger};
+/// use actix_web2::App;
+///
+/// fn main() {
+///     let app = App::new().middleware(
+///         Logger::structured(|record: AccessLog| {
+///             log::info!("{}", serde_json::to_string(&record).unwrap())
+///         })
+///         .header("user-agent"),
+///     );
+/// }
+/// ```
+pub trait AccessLogSink {
+    /// Write access log record
+    fn write(&self, record: AccessLog);
+}
+
+impl<F> AccessLogSink for F
+where
+    F: Fn(AccessLog),
+{
+    fn write(&self, record: AccessLog) {
+        (self)(record)
+    }
+}
+
+impl AccessLogSink for mpsc::Sender<AccessLog> {
+    fn write(&self, record: AccessLog) {
+        if self.send(record).is_err() {
+            warn!("Access log receiver is gone");
+        }
+    }
+}
 
-        unimplemented!()
+impl AccessLogSink for UnboundedSender<AccessLog> {
+    fn write(&self, record: AccessLog) {
+        if self.unbounded_send(record).is_err() {
+            warn!("Access log receiver is gone");
+        }
     }
 }
 
-// impl<S> Middleware<S> for Logger {
-//     fn start(&self, req: &ServiceRequest<S>) -> Result<Started> {
-//         if !self.exclude.contains(req.path()) {
-//             req.extensions_mut().insert(StartTime(time::now()));
-//         }
-//         Ok(Started::Done)
-//     }
+/// Access log sink that writes records as newline delimited json
+///
+/// ```rust,no_run
+/// use std::fs::OpenOptions;
+/// use actix_web2::middleware::{AccessLogWriter, Logger};
+///
+/// fn main() {
+///     let file = OpenOptions::new()
+///         .create(true)
+///         .append(true)
+///         .open("access.log")
+///         .unwrap();
+///     let logger = Logger::structured(AccessLogWriter::new(file));
+/// }
+/// ```
+pub struct AccessLogWriter<W> {
+    writer: RefCell<W>,
+}
 
-//     fn finish(&self, req: &ServiceRequest<S>, resp: &Response) -> Finished {
-//         self.log(req, resp);
-//         Finished::Done
-//     }
-// }
+impl<W: io::Write> AccessLogWriter<W> {
+    /// Create sink for the writer
+    pub fn new(writer: W) -> Self {
+        AccessLogWriter {
+            writer: RefCell::new(writer),
+        }
+    }
+}
+
+impl<W: io::Write> AccessLogSink for AccessLogWriter<W> {
+    fn write(&self, record: AccessLog) {
+        let mut writer = self.writer.borrow_mut();
+        let res = serde_json::to_writer(&mut *writer, &record)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"));
+        if let Err(e) = res {
+            warn!("Can not write access log record: {}", e);
+        }
+    }
+}
 
 /// A formatting style for the `Logger`, consisting of multiple
 /// `FormatText`s concatenated into one line.
@@ -192,7 +642,8 @@ impl Format {
     /// Returns `None` if the format string syntax is incorrect.
     pub fn new(s: &str) -> Format {
         trace!("Access log format: {}", s);
-        let fmt = Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe])|[atPrsbTD]?)").unwrap();
+        let fmt =
+            Regex::new(r"%(\{([A-Za-z0-9\-_]+)\}([ioe]|x[io])|[atPrsbTD]?)").unwrap();
 
         let mut idx = 0;
         let mut results = Vec::new();
@@ -209,6 +660,8 @@ impl Format {
                     "i" => FormatText::RequestHeader(key.as_str().to_owned()),
                     "o" => FormatText::ResponseHeader(key.as_str().to_owned()),
                     "e" => FormatText::EnvironHeader(key.as_str().to_owned()),
+                    "xi" => FormatText::CustomRequest(key.as_str().to_owned(), None),
+                    "xo" => FormatText::CustomResponse(key.as_str().to_owned(), None),
                     _ => unreachable!(),
                 })
             } else {
@@ -251,36 +704,41 @@ pub enum FormatText {
     RequestHeader(String),
     ResponseHeader(String),
     EnvironHeader(String),
+    CustomRequest(String, Option<CustomRequestFn>),
+    CustomResponse(String, Option<CustomResponseFn>),
+}
+
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct CustomRequestFn(Rc<Fn(&HttpRequest) -> String>);
+
+impl fmt::Debug for CustomRequestFn {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("custom_request_fn")
+    }
+}
+
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct CustomResponseFn(Rc<Fn(&HttpRequest, &ResponseHead) -> String>);
+
+impl fmt::Debug for CustomResponseFn {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("custom_response_fn")
+    }
 }
 
 impl FormatText {
-    fn render<S>(
-        &self, fmt: &mut Formatter, req: &ServiceRequest<S>, resp: &Response,
+    fn render(
+        &self,
+        fmt: &mut Formatter,
+        size: usize,
         entry_time: time::Tm,
     ) -> Result<(), fmt::Error> {
         match *self {
             FormatText::Str(ref string) => fmt.write_str(string),
             FormatText::Percent => "%".fmt(fmt),
-            FormatText::RequestLine => {
-                if req.query_string().is_empty() {
-                    fmt.write_fmt(format_args!(
-                        "{} {} {:?}",
-                        req.method(),
-                        req.path(),
-                        req.version()
-                    ))
-                } else {
-                    fmt.write_fmt(format_args!(
-                        "{} {}?{} {:?}",
-                        req.method(),
-                        req.path(),
-                        req.query_string(),
-                        req.version()
-                    ))
-                }
-            }
-            FormatText::ResponseStatus => resp.status().as_u16().fmt(fmt),
-            FormatText::ResponseSize => resp.response_size().fmt(fmt),
+            FormatText::ResponseSize => size.fmt(fmt),
             FormatText::Time => {
                 let rt = time::now() - entry_time;
                 let rt = (rt.num_nanoseconds().unwrap_or(0) as f64) / 1_000_000_000.0;
@@ -291,19 +749,31 @@ impl FormatText {
                 let rt = (rt.num_nanoseconds().unwrap_or(0) as f64) / 1_000_000.0;
                 fmt.write_fmt(format_args!("{:.6}", rt))
             }
-            FormatText::RemoteAddr => {
-                if let Some(remote) = req.connection_info().remote() {
-                    return remote.fmt(fmt);
-                } else {
-                    "-".fmt(fmt)
-                }
+            _ => Ok(()),
+        }
+    }
+
+    fn render_response<B>(&mut self, res: &ServiceResponse<B>) {
+        match *self {
+            // handler could update request extensions, so custom request
+            // placeholders are rendered with the response
+            FormatText::CustomRequest(_, ref f) => {
+                *self = FormatText::Str(match f {
+                    Some(f) => (f.0)(res.request()),
+                    None => "-".to_owned(),
+                })
             }
-            FormatText::RequestTime => entry_time
-                .strftime("[%d/%b/%Y:%H:%M:%S %z]")
-                .unwrap()
-                .fmt(fmt),
-            FormatText::RequestHeader(ref name) => {
-                let s = if let Some(val) = req.headers().get(name) {
+            FormatText::CustomResponse(_, ref f) => {
+                *self = FormatText::Str(match f {
+                    Some(f) => (f.0)(res.request(), res.response().head()),
+                    None => "-".to_owned(),
+                })
+            }
+            FormatText::ResponseStatus => {
+                *self = FormatText::Str(format!("{}", res.response().status().as_u16()))
+            }
+            FormatText::ResponseHeader(ref name) => {
+                let s = if let Some(val) = res.headers().get(name) {
                     if let Ok(s) = val.to_str() {
                         s
                     } else {
@@ -312,10 +782,48 @@ impl FormatText {
                 } else {
                     "-"
                 };
-                fmt.write_fmt(format_args!("{}", s))
+                *self = FormatText::Str(s.to_string())
             }
-            FormatText::ResponseHeader(ref name) => {
-                let s = if let Some(val) = resp.headers().get(name) {
+            _ => (),
+        }
+    }
+
+    fn render_request(&mut self, now: time::Tm, req: &HttpRequest) {
+        match *self {
+            FormatText::RequestLine => {
+                *self = if req.query_string().is_empty() {
+                    FormatText::Str(format!(
+                        "{} {} {:?}",
+                        req.method(),
+                        req.path(),
+                        req.version()
+                    ))
+                } else {
+                    FormatText::Str(format!(
+                        "{} {}?{} {:?}",
+                        req.method(),
+                        req.path(),
+                        req.query_string(),
+                        req.version()
+                    ))
+                };
+            }
+            FormatText::RequestTime => {
+                *self = FormatText::Str(format!(
+                    "{}",
+                    now.strftime("[%d/%b/%Y:%H:%M:%S %z]").unwrap()
+                ))
+            }
+            FormatText::RemoteAddr => {
+                let s = if let Some(remote) = ConnectionInfo::get(req).remote() {
+                    remote.to_string()
+                } else {
+                    "-".to_string()
+                };
+                *self = FormatText::Str(s)
+            }
+            FormatText::RequestHeader(ref name) => {
+                let s = if let Some(val) = req.headers().get(name) {
                     if let Ok(s) = val.to_str() {
                         s
                     } else {
@@ -324,15 +832,17 @@ impl FormatText {
                 } else {
                     "-"
                 };
-                fmt.write_fmt(format_args!("{}", s))
+                *self = FormatText::Str(s.to_string());
             }
             FormatText::EnvironHeader(ref name) => {
-                if let Ok(val) = env::var(name) {
-                    fmt.write_fmt(format_args!("{}", val))
+                let s = if let Ok(val) = env::var(name) {
+                    val
                 } else {
-                    "-".fmt(fmt)
-                }
+                    "-".to_string()
+                };
+                *self = FormatText::Str(s);
             }
+            _ => (),
         }
     }
 }
@@ -347,77 +857,237 @@ impl<'a> fmt::Display for FormatDisplay<'a> {
 
 #[cfg(test)]
 mod tests {
-    use time;
+    use std::rc::Rc;
 
-    use super::*;
-    use http::{header, StatusCode};
-    use test::TestRequest;
+    use actix_http::body::Body;
+    use actix_http::http::{header, StatusCode};
+    use actix_http::test::TestRequest;
+    use actix_http::{Extensions, Request, Response};
+    use actix_router::{Path, Url};
 
-    #[test]
-    fn test_logger() {
-        let logger = Logger::new("%% %{User-Agent}i %{X-Test}o %{HOME}e %D test");
+    use super::*;
 
-        let req = TestRequest::with_header(
-            header::USER_AGENT,
-            header::HeaderValue::from_static("ACTIX-WEB"),
-        ).finish();
-        let resp = Response::build(StatusCode::OK)
-            .header("X-Test", "ttt")
-            .force_close()
-            .finish();
+    fn request(req: Request) -> HttpRequest {
+        let (head, _) = req.into_parts();
+        let path = Path::new(Url::new(head.uri.clone()));
+        HttpRequest::new(head, path, Rc::new(Extensions::new()))
+    }
 
-        match logger.start(&req) {
-            Ok(Started::Done) => (),
-            _ => panic!(),
-        };
-        match logger.finish(&req, &resp) {
-            Finished::Done => (),
-            _ => panic!(),
-        }
+    fn render(format: &Format, size: usize) -> String {
         let entry_time = time::now();
         let render = |fmt: &mut Formatter| {
-            for unit in &logger.format.0 {
-                unit.render(fmt, &req, &resp, entry_time)?;
+            for unit in &format.0 {
+                unit.render(fmt, size, entry_time)?;
             }
             Ok(())
         };
-        let s = format!("{}", FormatDisplay(&render));
+        format!("{}", FormatDisplay(&render))
+    }
+
+    #[test]
+    fn test_logger() {
+        let mut format = Format::new("%% %{User-Agent}i %{X-Test}o %{HOME}e %D test");
+
+        let req = request(
+            TestRequest::with_header(
+                header::USER_AGENT,
+                header::HeaderValue::from_static("ACTIX-WEB"),
+            )
+            .finish(),
+        );
+        let res = ServiceResponse::new(
+            req.clone(),
+            Response::build(StatusCode::OK)
+                .header("X-Test", "ttt")
+                .force_close()
+                .finish(),
+        );
+
+        for unit in &mut format.0 {
+            unit.render_request(time::now(), &req);
+            unit.render_response(&res);
+        }
+        let s = render(&format, 0);
         assert!(s.contains("ACTIX-WEB ttt"));
     }
 
+    #[test]
+    fn test_custom_placeholders() {
+        let logger = Logger::new("%{user}xi %{cache}xo %{missing}xi")
+            .custom_request_replace("user", |req| req.path().to_owned())
+            .custom_response_replace("cache", |_, res| res.status.as_u16().to_string());
+        let mut format = logger.inner.format.clone().unwrap();
+
+        let req = request(TestRequest::with_uri("/john").finish());
+        let res = ServiceResponse::new(
+            req.clone(),
+            Response::build(StatusCode::NOT_FOUND).finish(),
+        );
+        for unit in &mut format.0 {
+            unit.render_request(time::now(), &req);
+            unit.render_response(&res);
+        }
+        assert_eq!(render(&format, 0), "/john 404 -");
+    }
+
     #[test]
     fn test_default_format() {
-        let format = Format::default();
+        let mut format = Format::default();
 
-        let req = TestRequest::with_header(
+        let mut req = TestRequest::with_header(
             header::USER_AGENT,
             header::HeaderValue::from_static("ACTIX-WEB"),
-        ).finish();
-        let resp = Response::build(StatusCode::OK).force_close().finish();
-        let entry_time = time::now();
+        )
+        .finish();
+        req.head_mut().peer_addr = Some("127.0.0.1:8080".parse().unwrap());
+        let req = request(req);
+        let res = ServiceResponse::new(
+            req.clone(),
+            Response::build(StatusCode::OK).force_close().finish(),
+        );
 
-        let render = |fmt: &mut Formatter| {
-            for unit in &format.0 {
-                unit.render(fmt, &req, &resp, entry_time)?;
-            }
-            Ok(())
-        };
-        let s = format!("{}", FormatDisplay(&render));
+        for unit in &mut format.0 {
+            unit.render_request(time::now(), &req);
+            unit.render_response(&res);
+        }
+        let s = render(&format, 11);
+        assert!(s.contains("127.0.0.1"));
         assert!(s.contains("GET / HTTP/1.1"));
-        assert!(s.contains("200 0"));
+        assert!(s.contains("200 11"));
         assert!(s.contains("ACTIX-WEB"));
 
-        let req = TestRequest::with_uri("/?test").finish();
-        let resp = Response::build(StatusCode::OK).force_close().finish();
-        let entry_time = time::now();
+        let mut format = Format::default();
+        let req = request(TestRequest::with_uri("/?test").finish());
+        for unit in &mut format.0 {
+            unit.render_request(time::now(), &req);
+        }
+        let s = render(&format, 0);
+        assert!(s.contains("- \"GET /?test HTTP/1.1\""));
+    }
 
-        let render = |fmt: &mut Formatter| {
-            for unit in &format.0 {
-                unit.render(fmt, &req, &resp, entry_time)?;
-            }
-            Ok(())
+    #[test]
+    fn test_stream_log_size() {
+        let mut body = StreamLog {
+            body: ResponseBody::Other(Body::from("hello world")),
+            format: None,
+            access: None,
+            size: 0,
+            time: time::now(),
         };
-        let s = format!("{}", FormatDisplay(&render));
-        assert!(s.contains("GET /?test HTTP/1.1"));
+        while let Async::Ready(Some(_)) = body.poll_next().unwrap() {}
+        assert_eq!(body.size, 11);
+    }
+
+    #[test]
+    fn test_access_log() {
+        let mut req = TestRequest::with_uri("/test?q=1")
+            .header(header::USER_AGENT, "ACTIX-WEB")
+            .finish();
+        req.head_mut().peer_addr = Some("127.0.0.1:8080".parse().unwrap());
+        let req = request(req);
+
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let records2 = records.clone();
+        let sink = move |record: AccessLog| records2.borrow_mut().push(record);
+
+        let mut record =
+            AccessLog::new(time::now(), &req, &[header::USER_AGENT, header::REFERER]);
+        record.status = StatusCode::NOT_FOUND;
+        let mut body = StreamLog {
+            body: ResponseBody::Other(Body::from("not found")),
+            format: None,
+            access: Some((Rc::new(sink) as Rc<AccessLogSink>, record)),
+            size: 0,
+            time: time::now(),
+        };
+        while let Async::Ready(Some(_)) = body.poll_next().unwrap() {}
+        drop(body);
+
+        let record = records.borrow_mut().pop().unwrap();
+        assert_eq!(record.method(), Method::GET);
+        assert_eq!(record.path(), "/test");
+        assert_eq!(record.bytes(), 9);
+        assert_eq!(record.remote_addr(), Some("127.0.0.1"));
+        assert_eq!(record.header("user-agent"), Some("ACTIX-WEB"));
+        assert_eq!(record.header("referer"), None);
+
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value["method"], "GET");
+        assert_eq!(value["status"], 404);
+        assert_eq!(value["bytes"], 9);
+        assert_eq!(value["remote_addr"], "127.0.0.1");
+        assert_eq!(value["headers"]["user-agent"], "ACTIX-WEB");
+        assert!(value["duration"].is_f64());
+    }
+
+    #[test]
+    fn test_access_log_writer() {
+        let req = request(TestRequest::default().finish());
+        let writer = AccessLogWriter::new(Vec::new());
+        writer.write(AccessLog::new(time::now(), &req, &[]));
+        writer.write(AccessLog::new(time::now(), &req, &[]));
+
+        let out = writer.writer.into_inner();
+        let lines: Vec<_> = out.split(|b| *b == b'\n').collect();
+        assert_eq!(lines.len(), 3);
+        let value: serde_json::Value = serde_json::from_slice(lines[0]).unwrap();
+        assert_eq!(value["path"], "/");
+        assert_eq!(value["remote_addr"], serde_json::Value::Null);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_structured_custom_placeholder() {
+        Logger::structured(|_: AccessLog| ())
+            .custom_request_replace("user", |req| req.path().to_owned());
+    }
+
+    #[test]
+    fn test_logger_middleware() {
+        use actix_service::{IntoNewService, NewService};
+        use futures::stream;
+
+        use crate::App;
+
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let records2 = records.clone();
+        let sink = move |record: AccessLog| records2.borrow_mut().push(record);
+
+        let mut srv = App::new()
+            .resource("/stream", |r| {
+                r.to(|| {
+                    Response::Ok().streaming(stream::iter_ok::<_, Error>(vec![
+                        Bytes::from_static(b"hello"),
+                        Bytes::from_static(b" world"),
+                    ]))
+                })
+            })
+            .middleware(Logger::structured(sink))
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap();
+
+        let res = srv
+            .call(TestRequest::with_uri("/stream").finish())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let mut body = None;
+        res.map_body(|_, b| {
+            body = Some(b);
+            ResponseBody::Other(Body::Empty)
+        });
+        let mut body = body.unwrap();
+        while let Async::Ready(Some(_)) = body.poll_next().unwrap() {}
+
+        // record is written once the body is dropped
+        assert!(records.borrow().is_empty());
+        drop(body);
+        let record = records.borrow_mut().pop().unwrap();
+        assert_eq!(record.path(), "/stream");
+        assert_eq!(record.status(), StatusCode::OK);
+        assert_eq!(record.bytes(), 11);
     }
 }
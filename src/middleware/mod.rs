@@ -7,11 +7,19 @@ use futures::future::{ok, FutureResult};
 mod compress;
 #[cfg(any(feature = "brotli", feature = "flate2"))]
 pub use self::compress::Compress;
+#[cfg(any(feature = "brotli", feature = "flate2"))]
+mod decompress;
+#[cfg(any(feature = "brotli", feature = "flate2"))]
+pub use self::decompress::Decompress;
 
+mod cors;
 mod defaultheaders;
 mod errhandlers;
 mod logger;
+pub use self::cors::{Cors, CorsError};
 pub use self::defaultheaders::DefaultHeaders;
+pub use self::errhandlers::{ErrorHandlerResponse, ErrorHandlers};
+pub use self::logger::{AccessLog, AccessLogSink, AccessLogWriter, Logger};
 
 /// Helper for middleware service factory
 pub struct MiddlewareFactory<T, S>
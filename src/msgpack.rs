@@ -0,0 +1,165 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use actix_http::error::{Error, PayloadError};
+use actix_http::Response;
+use bytes::Bytes;
+use futures::future::FutureResult;
+use futures::{Future, Stream};
+use mime::Mime;
+use rmp_serde;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::binary::{read_body, respond, BinaryConfig, BinaryPayloadError};
+use crate::handler::FromRequest;
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+use crate::service::ServiceRequest;
+
+/// MessagePack helper
+///
+/// Works the same way as [**Json**](struct.Json.html), but uses compact
+/// binary *MessagePack* format. Requires `msgpack` feature.
+///
+/// To extract typed information from request's body, the type `T` must
+/// implement the `Deserialize` trait from *serde*. Request's content type
+/// must be `application/msgpack` or `application/x-msgpack`.
+///
+/// [**MsgPackConfig**](dev/type.MsgPackConfig.html) allows to configure
+/// extraction process.
+///
+/// ## Example
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{App, MsgPack};
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// /// echo `Info` back as `application/msgpack` response
+/// fn index(info: MsgPack<Info>) -> MsgPack<Info> {
+///     info
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/index.html", |r| r.post(index));
+/// }
+/// ```
+pub struct MsgPack<T>(pub T);
+
+impl<T> MsgPack<T> {
+    /// Deconstruct to an inner value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for MsgPack<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for MsgPack<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> fmt::Debug for MsgPack<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MsgPack: {:?}", self.0)
+    }
+}
+
+impl<T> fmt::Display for MsgPack<T>
+where
+    T: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl<T: Serialize> Responder for MsgPack<T> {
+    type Error = Error;
+    type Future = FutureResult<Response, Error>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        respond(rmp_serde::to_vec_named(&self.0), "application/msgpack")
+    }
+}
+
+impl<T, P> FromRequest<P> for MsgPack<T>
+where
+    T: DeserializeOwned + 'static,
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Error = Error;
+    type Future = Box<Future<Item = Self, Error = Error>>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        Box::new(
+            read_body(req, is_msgpack, |body| rmp_serde::from_slice::<T>(body))
+                .map(MsgPack),
+        )
+    }
+}
+
+fn is_msgpack(mime: &Mime) -> bool {
+    mime.type_() == mime::APPLICATION
+        && (mime.subtype() == "msgpack" || mime.subtype() == "x-msgpack")
+}
+
+/// A set of errors that can occur during parsing MessagePack payloads
+pub type MsgPackPayloadError = BinaryPayloadError<rmp_serde::decode::Error>;
+
+/// MsgPack extractor configuration
+///
+/// ```rust
+/// use actix_http::error;
+/// use actix_web2::{dev, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::MsgPackConfig| {
+///         cfg.limit(4096)   // <- change msgpack extractor configuration
+///            .error_handler(|err, _| {  // <- create custom error response
+///                error::InternalError::from_response(
+///                    err, HttpResponse::Conflict().finish()).into()
+///            });
+///     });
+/// }
+/// ```
+pub type MsgPackConfig = BinaryConfig<rmp_serde::decode::Error>;
+
+#[cfg(test)]
+mod tests {
+    use actix_http::error::ResponseError;
+    use actix_http::http::StatusCode;
+
+    use super::*;
+
+    #[test]
+    fn test_msgpack_content_type() {
+        assert!(is_msgpack(&"application/msgpack".parse().unwrap()));
+        assert!(is_msgpack(&"application/x-msgpack".parse().unwrap()));
+        assert!(!is_msgpack(&mime::APPLICATION_JSON));
+    }
+
+    #[test]
+    fn test_msgpack_error() {
+        let e = rmp_serde::from_slice::<String>(b"\xc1").unwrap_err();
+        let e: MsgPackPayloadError = BinaryPayloadError::Deserialize(e);
+        assert_eq!(e.error_response().status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,436 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use actix_http::error::{
+    Error, ErrorUnsupportedMediaType, JsonPayloadError, PayloadError,
+};
+use actix_http::http::StatusCode;
+use actix_http::{HttpMessage, Response};
+use bytes::{Bytes, BytesMut};
+use futures::future::{err, ok, FutureResult};
+use futures::{Async, Poll, Stream};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+
+use crate::extractor::load_config;
+use crate::handler::FromRequest;
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+use crate::service::ServiceRequest;
+
+/// Newline-delimited json stream
+///
+/// `NdJson` can be used for streaming extraction of typed items from
+/// `application/x-ndjson` request's payload and for streaming response
+/// generation.
+///
+/// As an extractor, `NdJson<T>` is a `Stream` of `T` items. Lines are
+/// parsed as soon as they arrive from the payload, max size of a single
+/// line is configured with [**NdJsonConfig**](dev/struct.NdJsonConfig.html).
+/// Empty lines are skipped.
+///
+/// ## Example
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{App, Error, NdJson};
+/// use futures::{Future, Stream};
+///
+/// #[derive(Deserialize)]
+/// struct Record {
+///     id: u64,
+/// }
+///
+/// /// count records without buffering whole payload
+/// fn ingest(records: NdJson<Record>) -> impl Future<Item = String, Error = Error> {
+///     records.fold(0, |count, _| Ok::<_, Error>(count + 1))
+///         .map(|count| format!("{} records", count))
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/ingest", |r| r.to_async(ingest));
+/// }
+/// ```
+///
+/// As a responder, `NdJson` serializes each item of the stream to a
+/// separate line of the chunked `application/x-ndjson` response.
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{Error, NdJson};
+/// use futures::stream;
+///
+/// #[derive(Serialize)]
+/// struct Record {
+///     id: u64,
+/// }
+///
+/// fn export() -> NdJson<Record> {
+///     NdJson::new(stream::iter_ok::<_, Error>((0..1_000_000).map(|id| Record { id })))
+/// }
+/// # fn main() {}
+/// ```
+pub struct NdJson<T> {
+    stream: Box<Stream<Item = T, Error = Error>>,
+}
+
+impl<T> NdJson<T> {
+    /// Create `NdJson` from a stream of items
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = T> + 'static,
+        S::Error: Into<Error>,
+    {
+        NdJson {
+            stream: Box::new(stream.map_err(|e| e.into())),
+        }
+    }
+}
+
+impl<T> Stream for NdJson<T> {
+    type Item = T;
+    type Error = Error;
+
+    #[inline]
+    fn poll(&mut self) -> Poll<Option<T>, Error> {
+        self.stream.poll()
+    }
+}
+
+impl<T> fmt::Debug for NdJson<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NdJson")
+    }
+}
+
+impl<T, P> FromRequest<P> for NdJson<T>
+where
+    T: DeserializeOwned + 'static,
+    P: Stream<Item = Bytes, Error = PayloadError> + 'static,
+{
+    type Error = Error;
+    type Future = FutureResult<Self, Error>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        let cfg = load_config::<NdJsonConfig>(req);
+
+        // check content-type
+        let ndjson = if let Ok(Some(mime)) = req.mime_type() {
+            mime.type_() == mime::APPLICATION && mime.subtype() == "x-ndjson"
+        } else {
+            false
+        };
+        if !ndjson {
+            return err(ErrorUnsupportedMediaType(
+                "Content-Type application/x-ndjson is expected",
+            ));
+        }
+
+        ok(NdJson::new(NdJsonDecoder::new(
+            req.take_payload(),
+            cfg.limit,
+        )))
+    }
+}
+
+impl<T: Serialize + 'static> Responder for NdJson<T> {
+    type Error = Error;
+    type Future = FutureResult<Response, Error>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        ok(Response::build(StatusCode::OK)
+            .content_type("application/x-ndjson")
+            .streaming(self.stream.and_then(|item| {
+                let mut line = serde_json::to_vec(&item)?;
+                line.push(b'\n');
+                Ok(Bytes::from(line))
+            })))
+    }
+}
+
+/// NdJson extractor configuration
+///
+/// ```rust
+/// use actix_web2::{dev, App};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::NdJsonConfig| {
+///         cfg.limit(4096);  // <- max size of a single line
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct NdJsonConfig {
+    limit: usize,
+}
+
+impl NdJsonConfig {
+    /// Change max size of a single line. By default max size is 64Kb
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for NdJsonConfig {
+    fn default() -> Self {
+        NdJsonConfig { limit: 65_536 }
+    }
+}
+
+/// Stream that splits payload to lines and deserializes them
+struct NdJsonDecoder<S, T> {
+    stream: S,
+    buf: BytesMut,
+    checked: usize,
+    limit: usize,
+    eof: bool,
+    _t: PhantomData<T>,
+}
+
+impl<S, T> NdJsonDecoder<S, T> {
+    fn new(stream: S, limit: usize) -> Self {
+        NdJsonDecoder {
+            stream,
+            limit,
+            buf: BytesMut::new(),
+            checked: 0,
+            eof: false,
+            _t: PhantomData,
+        }
+    }
+}
+
+impl<S, T> Stream for NdJsonDecoder<S, T>
+where
+    S: Stream<Item = Bytes, Error = PayloadError>,
+    T: DeserializeOwned,
+{
+    type Item = T;
+    type Error = JsonPayloadError;
+
+    fn poll(&mut self) -> Poll<Option<T>, JsonPayloadError> {
+        loop {
+            let pos = self.buf[self.checked..].iter().position(|b| *b == b'\n');
+            let line = if let Some(pos) = pos {
+                let line = self.buf.split_to(self.checked + pos + 1);
+                self.checked = 0;
+                Some(line)
+            } else if self.eof && !self.buf.is_empty() {
+                Some(self.buf.take())
+            } else {
+                self.checked = self.buf.len();
+                None
+            };
+
+            if let Some(line) = line {
+                let line = trim_line(&line);
+                if line.len() > self.limit {
+                    return Err(JsonPayloadError::Overflow);
+                }
+                if line.is_empty() {
+                    continue;
+                }
+                return serde_json::from_slice(line)
+                    .map(|item| Async::Ready(Some(item)))
+                    .map_err(JsonPayloadError::Deserialize);
+            }
+
+            if self.buf.len() > self.limit {
+                return Err(JsonPayloadError::Overflow);
+            }
+            if self.eof {
+                return Ok(Async::Ready(None));
+            }
+
+            match self.stream.poll().map_err(JsonPayloadError::Payload)? {
+                Async::Ready(Some(chunk)) => self.buf.extend_from_slice(&chunk),
+                Async::Ready(None) => self.eof = true,
+                Async::NotReady => return Ok(Async::NotReady),
+            }
+        }
+    }
+}
+
+/// Strip line terminator and surrounding whitespace
+fn trim_line(line: &[u8]) -> &[u8] {
+    let start = line
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or_else(|| line.len());
+    let end = line
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(start, |pos| pos + 1);
+    &line[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use actix_http::body::{Body, MessageBody, ResponseBody};
+    use actix_http::http::header::{self, CONTENT_TYPE};
+    use actix_http::test::TestRequest;
+    use actix_http::{Extensions, PayloadStream};
+    use actix_router::{Path, Url};
+    use futures::stream::iter_ok;
+    use futures::Future;
+    use serde_derive::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Item {
+        id: u32,
+    }
+
+    fn service_request(
+        content_type: &str,
+        body: &'static [u8],
+        cfg: NdJsonConfig,
+    ) -> ServiceRequest<PayloadStream> {
+        let req = TestRequest::with_header(header::CONTENT_TYPE, content_type)
+            .set_payload(Bytes::from_static(body))
+            .finish();
+        let path = Path::new(Url::new(req.uri().clone()));
+        let mut extensions = Extensions::new();
+        extensions.insert(cfg);
+        ServiceRequest::new(path, req, Rc::new(extensions))
+    }
+
+    fn status(e: Error) -> StatusCode {
+        e.as_response_error().error_response().status()
+    }
+
+    fn decoder(
+        chunks: Vec<&'static [u8]>,
+        limit: usize,
+    ) -> NdJsonDecoder<impl Stream<Item = Bytes, Error = PayloadError>, Item> {
+        NdJsonDecoder::new(
+            iter_ok::<_, PayloadError>(chunks.into_iter().map(Bytes::from_static)),
+            limit,
+        )
+    }
+
+    #[test]
+    fn test_decoder() {
+        let items = decoder(
+            vec![b"{\"id\": 1}\n{\"i", b"d\": 2}\r\n\n", b"{\"id\": 3}"],
+            1024,
+        )
+        .collect()
+        .wait()
+        .unwrap();
+        assert_eq!(items, vec![Item { id: 1 }, Item { id: 2 }, Item { id: 3 }]);
+    }
+
+    #[test]
+    fn test_decoder_overflow() {
+        let res = decoder(vec![b"{\"id\": 1}\n", b"{\"id\": 1000000}\n"], 10)
+            .collect()
+            .wait();
+        match res {
+            Err(JsonPayloadError::Overflow) => (),
+            _ => panic!(),
+        }
+
+        let res = decoder(vec![b"{\"id\": 1}\n", b"not json\n"], 1024)
+            .collect()
+            .wait();
+        match res {
+            Err(JsonPayloadError::Deserialize(_)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut req = service_request(
+            "application/x-ndjson",
+            b"{\"id\": 1}\n\n{\"id\": 2}\n",
+            NdJsonConfig::default(),
+        );
+        let items = NdJson::<Item>::from_request(&mut req)
+            .wait()
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(items, vec![Item { id: 1 }, Item { id: 2 }]);
+
+        let mut req = service_request(
+            "application/json",
+            b"{\"id\": 1}\n",
+            NdJsonConfig::default(),
+        );
+        let e = NdJson::<Item>::from_request(&mut req).wait().err().unwrap();
+        assert_eq!(status(e), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn test_extract_limit() {
+        let mut cfg = NdJsonConfig::default();
+        cfg.limit(10);
+
+        // every line fits into the limit, whole payload does not
+        let mut req = service_request(
+            "application/x-ndjson",
+            b"{\"id\": 1}\n{\"id\": 2}\n{\"id\": 3}\n",
+            cfg.clone(),
+        );
+        let items = NdJson::<Item>::from_request(&mut req)
+            .wait()
+            .unwrap()
+            .collect()
+            .wait()
+            .unwrap();
+        assert_eq!(items.len(), 3);
+
+        let mut req = service_request(
+            "application/x-ndjson",
+            b"{\"id\": 1}\n{\"id\": 1000000}\n",
+            cfg,
+        );
+        let e = NdJson::<Item>::from_request(&mut req)
+            .wait()
+            .unwrap()
+            .collect()
+            .wait()
+            .err()
+            .unwrap();
+        assert_eq!(status(e), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_responder() {
+        let (head, _) = TestRequest::default().finish().into_parts();
+        let path = Path::new(Url::new(head.uri.clone()));
+        let req = HttpRequest::new(head, path, Rc::new(Extensions::new()));
+
+        let items = iter_ok::<_, Error>(vec![Item { id: 1 }, Item { id: 2 }]);
+        let res = NdJson::new(items).respond_to(&req).wait().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "application/x-ndjson"
+        );
+
+        let mut body = None;
+        res.map_body(|_, b| {
+            body = Some(b);
+            ResponseBody::Other(Body::Empty)
+        });
+        let mut body = body.unwrap();
+        let mut buf = BytesMut::new();
+        while let Async::Ready(Some(chunk)) = body.poll_next().unwrap() {
+            buf.extend_from_slice(&chunk);
+        }
+        assert_eq!(
+            buf.freeze(),
+            Bytes::from_static(b"{\"id\":1}\n{\"id\":2}\n")
+        );
+    }
+}
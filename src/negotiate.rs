@@ -0,0 +1,363 @@
+use std::cmp;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use actix_http::error::{Error, ErrorInternalServerError};
+use actix_http::http::header::{ACCEPT, CONTENT_TYPE, VARY};
+use actix_http::http::StatusCode;
+use actix_http::Response;
+use bytes::Bytes;
+use futures::future::{err, ok, FutureResult};
+use mime::Mime;
+use serde::Serialize;
+use serde_json;
+use serde_urlencoded;
+
+use crate::extractor::load_config;
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+
+/// Media range of the `Accept` header with its quality
+///
+/// ```rust
+/// use actix_web2::dev::Accept;
+///
+/// fn main() {
+///     let accept = Accept::parse("text/*;q=0.5, application/json");
+///     let available = [mime::TEXT_PLAIN, mime::APPLICATION_JSON];
+///     assert_eq!(Accept::negotiate(&accept, &available), Some(&available[1]));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accept {
+    mime: Mime,
+    quality: f64,
+}
+
+impl Accept {
+    fn new(tag: &str) -> Option<Accept> {
+        let mime = Mime::from_str(tag.trim()).ok()?;
+        let quality = match mime.get_param("q") {
+            Some(q) => match f64::from_str(q.as_str()) {
+                Ok(q) if q >= 0.0 => q.min(1.0),
+                _ => 0.0,
+            },
+            None => 1.0,
+        };
+        Some(Accept { mime, quality })
+    }
+
+    /// Parse a raw Accept header value into a list ordered by quality.
+    ///
+    /// Media ranges with equal quality are ordered from more specific
+    /// to less specific. Malformed media ranges are skipped.
+    pub fn parse(raw: &str) -> Vec<Accept> {
+        let mut accept: Vec<_> = raw.split(',').filter_map(Accept::new).collect();
+        accept.sort_by(|a, b| {
+            b.quality
+                .partial_cmp(&a.quality)
+                .unwrap_or(cmp::Ordering::Equal)
+                .then(b.specificity().cmp(&a.specificity()))
+        });
+        accept
+    }
+
+    /// Media range, i.e. `text/*`
+    pub fn mime(&self) -> &Mime {
+        &self.mime
+    }
+
+    /// Quality of the media range, from `0.0` to `1.0`
+    pub fn quality(&self) -> f64 {
+        self.quality
+    }
+
+    /// Check if media type belongs to the media range
+    pub fn matches(&self, mime: &Mime) -> bool {
+        if self.mime.type_() == mime::STAR {
+            true
+        } else if self.mime.type_() != mime.type_() {
+            false
+        } else {
+            self.mime.subtype() == mime::STAR || self.mime.subtype() == mime.subtype()
+        }
+    }
+
+    /// Select the most acceptable media type from the available ones.
+    ///
+    /// Quality of a media type is taken from the most specific matching
+    /// media range. Ties are resolved in favor of the first available
+    /// media type. Empty `accept` list accepts anything.
+    pub fn negotiate<'a>(accept: &[Accept], available: &'a [Mime]) -> Option<&'a Mime> {
+        if accept.is_empty() {
+            return available.first();
+        }
+
+        let mut selected = None;
+        let mut best = 0.0;
+        for ct in available {
+            let quality = accept
+                .iter()
+                .filter(|a| a.matches(ct))
+                .max_by_key(|a| a.specificity())
+                .map_or(0.0, |a| a.quality);
+            if quality > best {
+                best = quality;
+                selected = Some(ct);
+            }
+        }
+        selected
+    }
+
+    fn specificity(&self) -> u8 {
+        if self.mime.type_() == mime::STAR {
+            0
+        } else if self.mime.subtype() == mime::STAR {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+/// Content negotiating responder
+///
+/// Serializes the value to the format most preferred by the request's
+/// `Accept` header. `application/json` and
+/// `application/x-www-form-urlencoded` are supported out of the box,
+/// other formats are registered with
+/// [**NegotiateConfig**](dev/struct.NegotiateConfig.html).
+/// Response is `406 Not Acceptable` if none of the formats is acceptable.
+/// `Vary: Accept` header is always set.
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{App, Negotiate};
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// fn index() -> Negotiate<User> {
+///     Negotiate(User { name: "john".to_owned() })
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/index.html", |r| r.get(index));
+/// }
+/// ```
+pub struct Negotiate<T>(pub T);
+
+impl<T: Serialize> Responder for Negotiate<T> {
+    type Error = Error;
+    type Future = FutureResult<Response, Error>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        let cfg = load_config::<NegotiateConfig>(req);
+        let accept = req
+            .headers()
+            .get(ACCEPT)
+            .and_then(|val| val.to_str().ok())
+            .map(Accept::parse)
+            .unwrap_or_default();
+
+        let mut available = vec![
+            mime::APPLICATION_JSON,
+            mime::APPLICATION_WWW_FORM_URLENCODED,
+        ];
+        for (ct, _) in &cfg.formats {
+            if !available.contains(ct) {
+                available.push(ct.clone());
+            }
+        }
+
+        let ct = match Accept::negotiate(&accept, &available) {
+            Some(ct) => ct,
+            None => {
+                return ok(Response::build(StatusCode::NOT_ACCEPTABLE)
+                    .header(VARY, "accept")
+                    .finish());
+            }
+        };
+
+        let body = if let Some(encoder) = cfg.encoder(ct) {
+            serde_json::to_value(&self.0)
+                .map_err(Error::from)
+                .and_then(|value| (*encoder)(&value))
+        } else if *ct == mime::APPLICATION_JSON {
+            serde_json::to_vec(&self.0)
+                .map(Bytes::from)
+                .map_err(Error::from)
+        } else {
+            serde_urlencoded::to_string(&self.0)
+                .map(Bytes::from)
+                .map_err(ErrorInternalServerError)
+        };
+
+        match body {
+            Ok(body) => ok(Response::build(StatusCode::OK)
+                .header(CONTENT_TYPE, ct.as_ref())
+                .header(VARY, "accept")
+                .body(body)),
+            Err(e) => err(e),
+        }
+    }
+}
+
+/// Negotiate responder configuration
+///
+/// Registers additional response formats. Format encoder converts
+/// `serde_json::Value` of the response to the raw response body.
+///
+/// ```rust
+/// use actix_web2::{dev, App};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::NegotiateConfig| {
+///         // plain text representation of the json value
+///         cfg.format("text/plain", |value| Ok(value.to_string().into()));
+///     });
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct NegotiateConfig {
+    formats: Vec<(Mime, Rc<Fn(&serde_json::Value) -> Result<Bytes, Error>>)>,
+}
+
+impl NegotiateConfig {
+    /// Register encoder for a content type, i.e. `application/x-yaml`.
+    ///
+    /// Encoder registered for `application/json` replaces the built-in one.
+    ///
+    /// Panics if content type is not a valid mime type.
+    pub fn format<F>(&mut self, content_type: &str, f: F) -> &mut Self
+    where
+        F: Fn(&serde_json::Value) -> Result<Bytes, Error> + 'static,
+    {
+        let mime = Mime::from_str(content_type).expect("Invalid content type");
+        self.formats.retain(|(m, _)| *m != mime);
+        self.formats.push((mime, Rc::new(f)));
+        self
+    }
+
+    fn encoder(
+        &self,
+        mime: &Mime,
+    ) -> Option<Rc<Fn(&serde_json::Value) -> Result<Bytes, Error>>> {
+        self.formats
+            .iter()
+            .find(|(m, _)| m == mime)
+            .map(|(_, encoder)| encoder.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::body::{Body, ResponseBody};
+    use actix_http::test::TestRequest;
+    use actix_http::Extensions;
+    use actix_router::{Path, Url};
+    use futures::Future;
+    use serde_derive::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct User {
+        name: &'static str,
+    }
+
+    fn respond(accept: Option<&str>, cfg: NegotiateConfig) -> Response {
+        let req = match accept {
+            Some(accept) => TestRequest::with_header(ACCEPT, accept).finish(),
+            None => TestRequest::default().finish(),
+        };
+        let (head, _) = req.into_parts();
+        let path = Path::new(Url::new(head.uri.clone()));
+        let mut extensions = Extensions::new();
+        extensions.insert(cfg);
+        let req = HttpRequest::new(head, path, Rc::new(extensions));
+        Negotiate(User { name: "john" })
+            .respond_to(&req)
+            .wait()
+            .unwrap()
+    }
+
+    fn body(res: &Response) -> Bytes {
+        match res.body() {
+            ResponseBody::Body(Body::Bytes(b)) | ResponseBody::Other(Body::Bytes(b)) => {
+                b.clone()
+            }
+            _ => Bytes::new(),
+        }
+    }
+
+    #[test]
+    fn test_accept_parse() {
+        let accept =
+            Accept::parse("text/*;q=0.5, text/html, */*;q=0.1, bad, text/plain");
+        let mimes: Vec<_> = accept.iter().map(|a| a.mime().to_string()).collect();
+        assert_eq!(
+            mimes,
+            vec!["text/html", "text/plain", "text/*;q=0.5", "*/*;q=0.1"]
+        );
+        assert_eq!(accept[2].quality(), 0.5);
+    }
+
+    #[test]
+    fn test_accept_negotiate() {
+        let available = [mime::APPLICATION_JSON, mime::TEXT_PLAIN];
+
+        let accept = Accept::parse("text/*;q=0.5, application/json");
+        assert_eq!(Accept::negotiate(&accept, &available), Some(&available[0]));
+
+        // most specific range wins
+        let accept = Accept::parse("*/*, application/json;q=0");
+        assert_eq!(Accept::negotiate(&accept, &available), Some(&available[1]));
+
+        let accept = Accept::parse("image/png");
+        assert_eq!(Accept::negotiate(&accept, &available), None);
+
+        assert_eq!(Accept::negotiate(&[], &available), Some(&available[0]));
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let res = respond(None, NegotiateConfig::default());
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept");
+        assert_eq!(body(&res), Bytes::from_static(b"{\"name\":\"john\"}"));
+
+        let res = respond(
+            Some("application/json;q=0.5, application/x-www-form-urlencoded"),
+            NegotiateConfig::default(),
+        );
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "application/x-www-form-urlencoded"
+        );
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept");
+        assert_eq!(body(&res), Bytes::from_static(b"name=john"));
+    }
+
+    #[test]
+    fn test_negotiate_format() {
+        let mut cfg = NegotiateConfig::default();
+        cfg.format("text/plain", |value| Ok(value["name"].to_string().into()));
+
+        let res = respond(Some("text/*, application/json;q=0.9"), cfg);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "text/plain");
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept");
+        assert_eq!(body(&res), Bytes::from_static(b"\"john\""));
+    }
+
+    #[test]
+    fn test_not_acceptable() {
+        let res = respond(Some("text/html, image/*"), NegotiateConfig::default());
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(res.headers().get(VARY).unwrap(), "accept");
+    }
+}
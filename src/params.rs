@@ -0,0 +1,320 @@
+//! Deserializer for percent-decoded path parameters
+use std::vec;
+
+use actix_router::{Path, Url};
+use percent_encoding::percent_decode;
+use serde::de::value::Error;
+use serde::de::{self, DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+
+use crate::query::ValueDeserializer;
+
+/// Percent-decode path parameters.
+///
+/// Encoded `/` is rejected unless `allow_slash` is set.
+pub(crate) fn decode(
+    path: &Path<Url>,
+    allow_slash: bool,
+) -> Result<Vec<(String, String)>, Error> {
+    path.iter()
+        .map(|(name, value)| {
+            if !allow_slash && (value.contains("%2F") || value.contains("%2f")) {
+                return Err(de::Error::custom(format!(
+                    "Encoded '/' is not allowed in path parameter: {}",
+                    name
+                )));
+            }
+            percent_decode(value.as_bytes())
+                .decode_utf8()
+                .map(|value| (name.to_owned(), value.into_owned()))
+                .map_err(|_| {
+                    de::Error::custom(format!("Can not decode path parameter: {}", name))
+                })
+        })
+        .collect()
+}
+
+/// Deserialize an instance of type `T` from path parameters.
+pub(crate) fn from_params<T: DeserializeOwned>(
+    params: Vec<(String, String)>,
+) -> Result<T, Error> {
+    T::deserialize(ParamsDeserializer(params))
+}
+
+macro_rules! deserialize_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                Deserializer::$method(self.into_single()?, visitor)
+            }
+        )*
+    };
+}
+
+struct ParamsDeserializer(Vec<(String, String)>);
+
+impl ParamsDeserializer {
+    fn into_single(mut self) -> Result<ValueDeserializer, Error> {
+        if self.0.len() != 1 {
+            return Err(de::Error::custom(format!(
+                "wrong number of parameters: {} expected 1",
+                self.0.len()
+            )));
+        }
+        Ok(ValueDeserializer(self.0.pop().unwrap().1))
+    }
+}
+
+impl<'de> Deserializer<'de> for ParamsDeserializer {
+    type Error = Error;
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(MapAccess {
+            iter: self.0.into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let values: Vec<_> = self.0.into_iter().map(|(_, value)| value).collect();
+        visitor.visit_seq(SeqAccess(values.into_iter()))
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.0.len() < len {
+            Err(de::Error::custom(format!(
+                "wrong number of parameters: {} expected {}",
+                self.0.len(),
+                len
+            )))
+        } else {
+            self.deserialize_seq(visitor)
+        }
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Deserializer::deserialize_enum(self.into_single()?, name, variants, visitor)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    deserialize_single!(
+        deserialize_any deserialize_option deserialize_bool
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_identifier
+        deserialize_bytes deserialize_byte_buf
+    );
+}
+
+struct MapAccess {
+    iter: vec::IntoIter<(String, String)>,
+    value: Option<String>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if let Some((key, value)) = self.iter.next() {
+            self.value = Some(value);
+            seed.deserialize(key.into_deserializer()).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(ValueDeserializer(value)),
+            None => Err(de::Error::custom("Value is missing")),
+        }
+    }
+}
+
+struct SeqAccess(vec::IntoIter<String>);
+
+impl<'de> de::SeqAccess<'de> for SeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.0.next() {
+            Some(value) => seed.deserialize(ValueDeserializer(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::Uri;
+    use actix_router::ResourceDef;
+    use serde_derive::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Info {
+        name: String,
+        id: u32,
+    }
+
+    fn params(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_params() {
+        let p = params(&[("name", "john doe"), ("id", "32")]);
+
+        let info: Info = from_params(p.clone()).unwrap();
+        assert_eq!(info.name, "john doe");
+        assert_eq!(info.id, 32);
+
+        let (name, id): (String, u32) = from_params(p.clone()).unwrap();
+        assert_eq!(name, "john doe");
+        assert_eq!(id, 32);
+
+        let values: Vec<String> = from_params(p.clone()).unwrap();
+        assert_eq!(values, vec!["john doe".to_owned(), "32".to_owned()]);
+
+        assert!(from_params::<String>(p).is_err());
+
+        let id: u32 = from_params(params(&[("id", "32")])).unwrap();
+        assert_eq!(id, 32);
+        assert!(from_params::<u32>(params(&[("id", "abc")])).is_err());
+    }
+
+    fn path(uri: &'static str) -> Path<Url> {
+        let mut path = Path::new(Url::new(Uri::from_static(uri)));
+        assert!(ResourceDef::new("/{name}/{id}").match_path(&mut path));
+        path
+    }
+
+    #[test]
+    fn test_decode() {
+        let params = decode(&path("/john%20doe/32"), false).unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("name".to_owned(), "john doe".to_owned()),
+                ("id".to_owned(), "32".to_owned()),
+            ]
+        );
+        let info: Info = from_params(params).unwrap();
+        assert_eq!(info.name, "john doe");
+    }
+
+    #[test]
+    fn test_decode_slash() {
+        assert!(decode(&path("/a%2Fb/32"), false).is_err());
+        assert!(decode(&path("/a%2fb/32"), false).is_err());
+
+        let params = decode(&path("/a%2Fb/32"), true).unwrap();
+        assert_eq!(params[0].1, "a/b");
+    }
+
+    #[test]
+    fn test_decode_invalid_utf8() {
+        assert!(decode(&path("/%FF%FE/32"), false).is_err());
+        assert!(decode(&path("/%C3/32"), true).is_err());
+    }
+}
@@ -0,0 +1,508 @@
+//! Query string deserializer with support for repeated keys and nested structures
+//!
+//! * `tag=a&tag=b` and `tag[]=a&tag[]=b` deserialize to a sequence
+//! * `filter[status]=open` deserializes to a nested struct or map
+//! * `item[0]=a&item[1]=b` deserializes to a sequence ordered by index
+use std::vec;
+
+use percent_encoding::percent_decode;
+use serde::de::value::Error;
+use serde::de::{self, DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// Max number of nested key segments, i.e. `a[b][c]` has 3 segments.
+const MAX_DEPTH: usize = 16;
+
+/// Deserialize an instance of type `T` from a query string.
+pub(crate) fn from_str<T: DeserializeOwned>(query: &str) -> Result<T, Error> {
+    let mut root = Node::Map(Vec::new());
+
+    for pair in query.split('&').filter(|s| !s.is_empty()) {
+        let mut items = pair.splitn(2, '=');
+        let key = decode(items.next().unwrap_or(""))?;
+        let value = decode(items.next().unwrap_or(""))?;
+        root.insert(&parse_key(&key)?, value)?;
+    }
+
+    T::deserialize(NodeDeserializer(root))
+}
+
+fn decode(s: &str) -> Result<String, Error> {
+    let s = s.replace('+', " ");
+    percent_decode(s.as_bytes())
+        .decode_utf8()
+        .map(|s| s.into_owned())
+        .map_err(|_| de::Error::custom("Can not decode query string"))
+}
+
+/// Split `a[b][]` key to `["a", "b"]`, empty segments mean "append".
+fn parse_key(key: &str) -> Result<Vec<String>, Error> {
+    let (name, mut rest) = match key.find('[') {
+        Some(idx) if idx > 0 => (&key[..idx], &key[idx..]),
+        _ => return Ok(vec![key.to_owned()]),
+    };
+
+    let mut path = vec![name.to_owned()];
+    while !rest.is_empty() {
+        if !rest.starts_with('[') {
+            return Ok(vec![key.to_owned()]);
+        }
+        match rest.find(']') {
+            Some(end) => {
+                if end > 1 {
+                    path.push(rest[1..end].to_owned());
+                }
+                rest = &rest[end + 1..];
+            }
+            None => return Ok(vec![key.to_owned()]),
+        }
+    }
+
+    if path.len() > MAX_DEPTH {
+        Err(de::Error::custom(format!("Query key is too deep: {}", key)))
+    } else {
+        Ok(path)
+    }
+}
+
+enum Node {
+    Value(Vec<String>),
+    Map(Vec<(String, Node)>),
+}
+
+impl Node {
+    fn insert(&mut self, path: &[String], value: String) -> Result<(), Error> {
+        let promote = match *self {
+            Node::Value(ref values) => !path.is_empty() && values.is_empty(),
+            Node::Map(_) => false,
+        };
+        if promote {
+            *self = Node::Map(Vec::new());
+        }
+
+        match *self {
+            Node::Value(ref mut values) if path.is_empty() => {
+                values.push(value);
+                Ok(())
+            }
+            Node::Map(ref mut entries) if !path.is_empty() => {
+                let idx = if let Some(idx) = entries.iter().position(|e| e.0 == path[0])
+                {
+                    idx
+                } else {
+                    entries.push((path[0].clone(), Node::Value(Vec::new())));
+                    entries.len() - 1
+                };
+                entries[idx].1.insert(&path[1..], value)
+            }
+            _ => Err(de::Error::custom(format!(
+                "Query key is used as a value and as a map: {}",
+                path.first().map(|s| s.as_str()).unwrap_or("")
+            ))),
+        }
+    }
+
+    fn into_value(self) -> Result<ValueDeserializer, Error> {
+        match self {
+            Node::Value(mut values) => match values.pop() {
+                Some(value) => Ok(ValueDeserializer(value)),
+                None => Err(de::Error::custom("Value is expected")),
+            },
+            Node::Map(_) => Err(de::Error::custom("Value is expected, got map")),
+        }
+    }
+
+    fn into_seq(self) -> vec::IntoIter<Node> {
+        match self {
+            Node::Value(values) => values
+                .into_iter()
+                .map(|v| Node::Value(vec![v]))
+                .collect::<Vec<_>>()
+                .into_iter(),
+            Node::Map(mut entries) => {
+                if entries.iter().all(|e| e.0.parse::<usize>().is_ok()) {
+                    entries.sort_by_key(|e| e.0.parse::<usize>().unwrap());
+                }
+                entries
+                    .into_iter()
+                    .map(|e| e.1)
+                    .collect::<Vec<_>>()
+                    .into_iter()
+            }
+        }
+    }
+}
+
+macro_rules! deserialize_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                Deserializer::$method(self.0.into_value()?, visitor)
+            }
+        )*
+    };
+}
+
+struct NodeDeserializer(Node);
+
+impl<'de> Deserializer<'de> for NodeDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Node::Map(entries) => visitor.visit_map(MapAccess {
+                iter: entries.into_iter(),
+                value: None,
+            }),
+            Node::Value(mut values) => {
+                if values.len() == 1 {
+                    let value = ValueDeserializer(values.pop().unwrap());
+                    Deserializer::deserialize_any(value, visitor)
+                } else {
+                    visitor.visit_seq(SeqAccess(Node::Value(values).into_seq()))
+                }
+            }
+        }
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Node::Map(entries) => visitor.visit_map(MapAccess {
+                iter: entries.into_iter(),
+                value: None,
+            }),
+            Node::Value(_) => Err(de::Error::custom("Map is expected, got value")),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqAccess(self.0.into_seq()))
+    }
+
+    fn deserialize_tuple<V>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Deserializer::deserialize_enum(self.0.into_value()?, name, variants, visitor)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    deserialize_value!(
+        deserialize_bool
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_identifier
+        deserialize_bytes deserialize_byte_buf
+    );
+}
+
+struct MapAccess {
+    iter: vec::IntoIter<(String, Node)>,
+    value: Option<Node>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if let Some((key, value)) = self.iter.next() {
+            self.value = Some(value);
+            seed.deserialize(key.into_deserializer()).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(NodeDeserializer(value)),
+            None => Err(de::Error::custom("Value is missing")),
+        }
+    }
+}
+
+struct SeqAccess(vec::IntoIter<Node>);
+
+impl<'de> de::SeqAccess<'de> for SeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.0.next() {
+            Some(node) => seed.deserialize(NodeDeserializer(node)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+macro_rules! parse_value {
+    ($method:ident, $visit:ident, $tp:ty) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            let v = self.0.parse::<$tp>().map_err(|_| {
+                de::Error::custom(format!(
+                    "can not parse {:?} to a {}",
+                    self.0,
+                    stringify!($tp)
+                ))
+            })?;
+            visitor.$visit(v)
+        }
+    };
+}
+
+pub(crate) struct ValueDeserializer(pub(crate) String);
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    parse_value!(deserialize_bool, visit_bool, bool);
+    parse_value!(deserialize_i8, visit_i8, i8);
+    parse_value!(deserialize_i16, visit_i16, i16);
+    parse_value!(deserialize_i32, visit_i32, i32);
+    parse_value!(deserialize_i64, visit_i64, i64);
+    parse_value!(deserialize_u8, visit_u8, u8);
+    parse_value!(deserialize_u16, visit_u16, u16);
+    parse_value!(deserialize_u32, visit_u32, u32);
+    parse_value!(deserialize_u64, visit_u64, u64);
+    parse_value!(deserialize_f32, visit_f32, f32);
+    parse_value!(deserialize_f64, visit_f64, f64);
+    parse_value!(deserialize_char, visit_char, char);
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_derive::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Status {
+        Open,
+        Closed,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Filter {
+        status: Status,
+        owner: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        id: u32,
+        #[serde(default)]
+        tag: Vec<String>,
+        filter: Filter,
+    }
+
+    #[test]
+    fn test_flat() {
+        let s: HashMap<String, String> = from_str("a=1&b=hello+world%21").unwrap();
+        assert_eq!(s["a"], "1");
+        assert_eq!(s["b"], "hello world!");
+    }
+
+    #[test]
+    fn test_repeated_keys() {
+        let s: HashMap<String, Vec<u32>> = from_str("a=1&a=2&b[]=3&b[]=4").unwrap();
+        assert_eq!(s["a"], vec![1, 2]);
+        assert_eq!(s["b"], vec![3, 4]);
+
+        let s: HashMap<String, Vec<String>> = from_str("a=1").unwrap();
+        assert_eq!(s["a"], vec!["1"]);
+
+        let s: HashMap<String, Vec<String>> = from_str("a[1]=y&a[0]=x").unwrap();
+        assert_eq!(s["a"], vec!["x", "y"]);
+    }
+
+    #[test]
+    fn test_nested() {
+        let s: Params =
+            from_str("id=10&tag=a&tag=b&filter%5Bstatus%5D=Open&filter[owner]=me")
+                .unwrap();
+        assert_eq!(
+            s,
+            Params {
+                id: 10,
+                tag: vec!["a".to_owned(), "b".to_owned()],
+                filter: Filter {
+                    status: Status::Open,
+                    owner: Some("me".to_owned()),
+                },
+            }
+        );
+
+        let s: Params = from_str("id=10&filter[status]=Closed").unwrap();
+        assert!(s.tag.is_empty());
+        assert_eq!(s.filter.status, Status::Closed);
+        assert_eq!(s.filter.owner, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(from_str::<Params>("id=abc&filter[status]=Open").is_err());
+        assert!(from_str::<Params>("id=1&filter=x&filter[status]=Open").is_err());
+        assert!(from_str::<HashMap<String, String>>(
+            "a[1][2][3][4][5][6][7][8][9][10][11][12][13][14][15][16]=1"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use std::fmt::Write;
+
+use actix_http::error::{Error, ErrorBadRequest, ErrorInternalServerError};
+use actix_http::http::header::LOCATION;
+use actix_http::http::StatusCode;
+use actix_http::Response;
+use futures::future::{err, ok, FutureResult};
+use percent_encoding::percent_decode;
+
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+
+/// Redirect responder
+///
+/// Target could be an absolute `http`/`https` url or a relative reference.
+/// Characters that are not allowed in urls are percent-encoded, existing
+/// percent-encoded sequences are kept as is. Responding with a target
+/// of other scheme, i.e. `javascript:`, fails with *500 Internal Server
+/// Error*.
+///
+/// ```rust
+/// use actix_web2::{App, Redirect};
+///
+/// fn login() -> Redirect {
+///     Redirect::see_other("/welcome")
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/login", |r| r.post(login));
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    status: StatusCode,
+    location: String,
+}
+
+impl Redirect {
+    /// *307 Temporary Redirect*, request method and body are preserved
+    pub fn temporary<T: AsRef<str>>(to: T) -> Redirect {
+        Redirect::new(StatusCode::TEMPORARY_REDIRECT, to)
+    }
+
+    /// *308 Permanent Redirect*, request method and body are preserved
+    pub fn permanent<T: AsRef<str>>(to: T) -> Redirect {
+        Redirect::new(StatusCode::PERMANENT_REDIRECT, to)
+    }
+
+    /// *303 See Other*, target is requested with `GET` method
+    pub fn see_other<T: AsRef<str>>(to: T) -> Redirect {
+        Redirect::new(StatusCode::SEE_OTHER, to)
+    }
+
+    /// *302 Found*, clients usually change method to `GET`
+    pub fn found<T: AsRef<str>>(to: T) -> Redirect {
+        Redirect::new(StatusCode::FOUND, to)
+    }
+
+    fn new<T: AsRef<str>>(status: StatusCode, to: T) -> Redirect {
+        Redirect {
+            status,
+            location: escape_location(to.as_ref()),
+        }
+    }
+
+    /// Escaped value of the `Location` header
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// Redirect status code
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl Responder for Redirect {
+    type Error = Error;
+    type Future = FutureResult<Response, Error>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        if !is_valid_location(&self.location) {
+            return err(ErrorInternalServerError(format!(
+                "Invalid redirect location: {}",
+                self.location
+            )));
+        }
+
+        ok(Response::build(self.status)
+            .header(LOCATION, self.location)
+            .finish())
+    }
+}
+
+/// Target of the `App::redirect()`, with path parameters of the source
+/// pattern substituted
+#[derive(Clone)]
+pub(crate) struct RedirectTarget {
+    to: String,
+    status: StatusCode,
+    relative: bool,
+}
+
+impl RedirectTarget {
+    /// Panics if target is not a valid location or refers to a parameter
+    /// that is not defined in the source pattern.
+    pub(crate) fn new(from: &str, to: &str, status: StatusCode) -> RedirectTarget {
+        let names = param_names(from);
+        for name in param_names(to) {
+            if !names.contains(&name) {
+                panic!("Redirect target refers to unknown parameter: {}", name);
+            }
+        }
+
+        let location = escape_location(&substitute(to, |_| "x".to_owned()));
+        if !is_valid_location(&location) {
+            panic!("Invalid redirect target: {}", to);
+        }
+        let relative = !has_scheme(&location);
+        if relative && location.starts_with("//") {
+            panic!("Protocol relative redirect target: {}", to);
+        }
+
+        RedirectTarget {
+            status,
+            relative,
+            to: to.to_owned(),
+        }
+    }
+
+    /// Substituted values are percent-encoded, redirect fails with
+    /// *400 Bad Request* if an empty parameter turns relative target into
+    /// a protocol-relative reference, i.e. `/{a:.*}/{b}` target with empty
+    /// `a` parameter.
+    pub(crate) fn redirect(&self, req: &HttpRequest) -> Result<Redirect, Error> {
+        let location = substitute(&self.to, |name| {
+            let value = req.match_info().get(name).unwrap_or("");
+            escape_segment(&percent_decode(value.as_bytes()).collect::<Vec<_>>())
+        });
+
+        if self.relative && location.starts_with("//") {
+            return Err(ErrorBadRequest("Invalid redirect location"));
+        }
+
+        Ok(Redirect::new(self.status, location))
+    }
+}
+
+/// Replace `{name}` segments of the pattern
+fn substitute<F>(pattern: &str, f: F) -> String
+where
+    F: Fn(&str) -> String,
+{
+    let mut location = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        location.push_str(&rest[..start]);
+        location.push_str(&f(&rest[start + 1..end]));
+        rest = &rest[end + 1..];
+    }
+    location.push_str(rest);
+    location
+}
+
+/// Percent-encode everything but unreserved characters
+fn escape_segment(value: &[u8]) -> String {
+    let mut segment = String::with_capacity(value.len());
+    for b in value {
+        match *b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                segment.push(*b as char)
+            }
+            _ => {
+                let _ = write!(segment, "%{:02X}", b);
+            }
+        }
+    }
+    segment
+}
+
+/// Names of the `{name}` or `{name:regex}` segments of the pattern
+fn param_names(pattern: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = &rest[start + 1..end];
+        names.push(name.split(':').next().unwrap_or(name));
+        rest = &rest[end + 1..];
+    }
+    names
+}
+
+/// Percent-encode characters that are not allowed in urls
+fn escape_location(to: &str) -> String {
+    let bytes = to.as_bytes();
+    let mut location = String::with_capacity(bytes.len());
+    for (idx, b) in bytes.iter().enumerate() {
+        let keep = match *b {
+            b'%' => {
+                bytes.len() > idx + 2
+                    && (bytes[idx + 1] as char).is_ascii_hexdigit()
+                    && (bytes[idx + 2] as char).is_ascii_hexdigit()
+            }
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => true,
+            b'-' | b'.' | b'_' | b'~' => true,
+            b':' | b'/' | b'?' | b'#' | b'[' | b']' | b'@' => true,
+            b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';'
+            | b'=' => true,
+            _ => false,
+        };
+        if keep {
+            location.push(*b as char);
+        } else {
+            let _ = write!(location, "%{:02X}", b);
+        }
+    }
+    location
+}
+
+/// Check if location starts with a scheme, i.e. `http:`
+fn has_scheme(location: &str) -> bool {
+    let end = location.find(|c| c == '/' || c == '?' || c == '#');
+    location[..end.unwrap_or_else(|| location.len())].contains(':')
+}
+
+/// Location must be a relative reference or `http`/`https` url
+fn is_valid_location(location: &str) -> bool {
+    if location.is_empty() {
+        return false;
+    }
+    let end = location.find(|c| c == '/' || c == '?' || c == '#');
+    match location[..end.unwrap_or_else(|| location.len())].find(':') {
+        Some(pos) => {
+            let scheme = location[..pos].to_lowercase();
+            (scheme == "http" || scheme == "https")
+                && location[pos + 1..].starts_with("//")
+                && location.len() > pos + 3
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use actix_http::test::TestRequest;
+    use actix_http::Extensions;
+    use actix_router::{Path, ResourceDef, Url};
+
+    use super::*;
+
+    #[test]
+    fn test_escape_location() {
+        assert_eq!(
+            Redirect::temporary("/a b/ü?q=<x>#f").location(),
+            "/a%20b/%C3%BC?q=%3Cx%3E#f"
+        );
+        assert_eq!(Redirect::found("/a%2Fb/100%").location(), "/a%2Fb/100%25");
+        assert_eq!(
+            Redirect::permanent("/a\r\nSet-Cookie: x").location(),
+            "/a%0D%0ASet-Cookie:%20x"
+        );
+        assert_eq!(Redirect::see_other("/").status(), StatusCode::SEE_OTHER);
+    }
+
+    #[test]
+    fn test_valid_location() {
+        assert!(is_valid_location("/path"));
+        assert!(is_valid_location("path/a:b"));
+        assert!(is_valid_location("https://example.com/"));
+        assert!(is_valid_location("HTTP://example.com"));
+        assert!(!is_valid_location("javascript:alert(1)"));
+        assert!(!is_valid_location("http:example.com"));
+        assert!(!is_valid_location(""));
+    }
+
+    #[test]
+    fn test_param_names() {
+        assert_eq!(param_names("/user/{id}/{tail:.*}"), vec!["id", "tail"]);
+        assert!(param_names("/static").is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_redirect_target_unknown_param() {
+        RedirectTarget::new("/user/{id}", "/u/{name}", StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_redirect_target_invalid() {
+        RedirectTarget::new("/go", "javascript:alert(1)", StatusCode::FOUND);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_redirect_target_protocol_relative() {
+        RedirectTarget::new("/go", "//evil.com", StatusCode::FOUND);
+    }
+
+    fn request(uri: &str, pattern: &str) -> HttpRequest {
+        let (head, _) = TestRequest::with_uri(uri).finish().into_parts();
+        let mut path = Path::new(Url::new(head.uri.clone()));
+        assert!(ResourceDef::new(pattern).match_path(&mut path));
+        HttpRequest::new(head, path, Rc::new(Extensions::new()))
+    }
+
+    #[test]
+    fn test_redirect_target() {
+        let target = RedirectTarget::new(
+            "/user/{id}",
+            "/users/{id}/profile",
+            StatusCode::PERMANENT_REDIRECT,
+        );
+        let redirect = target
+            .redirect(&request("/user/a%20b", "/user/{id}"))
+            .unwrap();
+        assert_eq!(redirect.location(), "/users/a%20b/profile");
+        assert_eq!(redirect.status(), StatusCode::PERMANENT_REDIRECT);
+
+        // substituted segments are encoded
+        let redirect = target
+            .redirect(&request("/user/a:b", "/user/{id}"))
+            .unwrap();
+        assert_eq!(redirect.location(), "/users/a%3Ab/profile");
+
+        let target = RedirectTarget::new(
+            "/docs/{page}",
+            "https://docs.rs/actix-web/{page}",
+            StatusCode::FOUND,
+        );
+        let redirect = target
+            .redirect(&request("/docs/%2F%2Fx", "/docs/{page}"))
+            .unwrap();
+        assert_eq!(redirect.location(), "https://docs.rs/actix-web/%2F%2Fx");
+    }
+
+    #[test]
+    fn test_redirect_target_open_redirect() {
+        // empty parameter turns target into a reference to another host
+        let target =
+            RedirectTarget::new("/go/{a:.*}/{b}", "/{a}/{b}", StatusCode::FOUND);
+        let e = target
+            .redirect(&request("/go//evil.com", "/go/{a:.*}/{b}"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            e.as_response_error().error_response().status(),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            target
+                .redirect(&request("/go/x/y", "/go/{a:.*}/{b}"))
+                .unwrap()
+                .location(),
+            "/x/y"
+        );
+
+        // separators of the substituted values are encoded
+        let target = RedirectTarget::new("/go/{to}", "{to}", StatusCode::FOUND);
+        assert!(target
+            .redirect(&request("/go/%2F%2Fevil.com", "/go/{to}"))
+            .unwrap()
+            .location()
+            .starts_with("%2F%2F"));
+        assert!(target
+            .redirect(&request("/go/http:%2F%2Fevil.com", "/go/{to}"))
+            .unwrap()
+            .location()
+            .starts_with("http%3A"));
+    }
+}
@@ -1,5 +1,6 @@
 use std::cell::{Ref, RefMut};
 use std::fmt;
+use std::net::SocketAddr;
 use std::ops::Deref;
 use std::rc::Rc;
 
@@ -9,11 +10,12 @@ use actix_router::{Path, Url};
 use futures::future::{ok, FutureResult};
 
 use crate::handler::FromRequest;
+use crate::info::ConnectionInfo;
 use crate::service::ServiceRequest;
 
 #[derive(Clone)]
 pub struct HttpRequest {
-    head: Message<RequestHead>,
+    head: Rc<Message<RequestHead>>,
     pub(crate) path: Path<Url>,
     extensions: Rc<Extensions>,
 }
@@ -26,7 +28,7 @@ impl HttpRequest {
         extensions: Rc<Extensions>,
     ) -> HttpRequest {
         HttpRequest {
-            head,
+            head: Rc::new(head),
             path,
             extensions,
         }
@@ -40,6 +42,12 @@ impl HttpRequest {
         &self.head
     }
 
+    /// Mutable reference to the request head, `None` if request is shared
+    #[inline]
+    pub(crate) fn head_mut(&mut self) -> Option<&mut RequestHead> {
+        Rc::get_mut(&mut self.head).map(|head| &mut **head)
+    }
+
     /// Request's uri.
     #[inline]
     pub fn uri(&self) -> &Uri {
@@ -111,11 +119,23 @@ impl HttpRequest {
         &self.extensions
     }
 
-    // /// Get *ConnectionInfo* for the correct request.
-    // #[inline]
-    // pub fn connection_info(&self) -> Ref<ConnectionInfo> {
-    //    ConnectionInfo::get(&*self)
-    // }
+    /// Get *ConnectionInfo* for the current request.
+    #[inline]
+    pub fn connection_info(&self) -> Ref<ConnectionInfo> {
+        ConnectionInfo::get(self)
+    }
+
+    /// Peer socket address
+    ///
+    /// Peer address is actual socket address, if proxy is used in front of
+    /// actix http server, then peer address would be address of this proxy.
+    ///
+    /// To get client connection information `connection_info()` method should
+    /// be used.
+    #[inline]
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.head().peer_addr
+    }
 }
 
 impl Deref for HttpRequest {
@@ -1,9 +1,12 @@
+use actix_http::body::{Body, BodyLength, MessageBody};
 use actix_http::dev::ResponseBuilder;
-use actix_http::http::StatusCode;
+use actix_http::error::ErrorInternalServerError;
+use actix_http::http::header::{HeaderName, HeaderValue, CONTENT_TYPE};
+use actix_http::http::{Error as HttpError, HeaderMap, HttpTryFrom, StatusCode};
 use actix_http::{Error, Response};
 use bytes::{Bytes, BytesMut};
 use futures::future::{err, ok, Either as EitherFuture, FutureResult};
-use futures::{Future, Poll};
+use futures::{try_ready, Async, Future, Poll, Stream};
 
 use crate::request::HttpRequest;
 
@@ -19,6 +22,148 @@ pub trait Responder {
 
     /// Convert itself to `AsyncResult` or `Error`.
     fn respond_to(self, req: &HttpRequest) -> Self::Future;
+
+    /// Override a status code for a responder.
+    ///
+    /// ```rust
+    /// # #[macro_use] extern crate serde_derive;
+    /// use actix_web2::{http::StatusCode, Json, Responder};
+    ///
+    /// #[derive(Serialize)]
+    /// struct User {
+    ///     id: u32,
+    /// }
+    ///
+    /// fn create() -> impl Responder {
+    ///     Json(User { id: 1 }).with_status(StatusCode::CREATED)
+    /// }
+    /// # fn main() {}
+    /// ```
+    fn with_status(self, status: StatusCode) -> CustomResponder<Self>
+    where
+        Self: Sized,
+    {
+        CustomResponder::new(self).with_status(status)
+    }
+
+    /// Add extra header to a responder's response, existing header
+    /// with the same name is replaced.
+    ///
+    /// ```rust
+    /// use actix_web2::Responder;
+    ///
+    /// fn index() -> impl Responder {
+    ///     "Welcome!".with_header("x-version", "1.2.3")
+    /// }
+    /// # fn main() {}
+    /// ```
+    fn with_header<K, V>(self, key: K, value: V) -> CustomResponder<Self>
+    where
+        Self: Sized,
+        HeaderName: HttpTryFrom<K>,
+        HeaderValue: HttpTryFrom<V>,
+    {
+        CustomResponder::new(self).with_header(key, value)
+    }
+}
+
+/// Responder wrapper that overrides status code and headers of
+/// the wrapped responder's response.
+pub struct CustomResponder<T> {
+    responder: T,
+    status: Option<StatusCode>,
+    headers: Option<HeaderMap>,
+    error: Option<HttpError>,
+}
+
+impl<T: Responder> CustomResponder<T> {
+    fn new(responder: T) -> Self {
+        CustomResponder {
+            responder,
+            status: None,
+            headers: None,
+            error: None,
+        }
+    }
+
+    /// Override a status code for the response.
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Add extra header to the response.
+    pub fn with_header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: HttpTryFrom<K>,
+        HeaderValue: HttpTryFrom<V>,
+    {
+        if self.headers.is_none() {
+            self.headers = Some(HeaderMap::new());
+        }
+
+        match HeaderName::try_from(key) {
+            Ok(key) => match HeaderValue::try_from(value) {
+                Ok(value) => {
+                    self.headers.as_mut().unwrap().append(key, value);
+                }
+                Err(e) => self.error = Some(e.into()),
+            },
+            Err(e) => self.error = Some(e.into()),
+        };
+        self
+    }
+}
+
+impl<T: Responder> Responder for CustomResponder<T> {
+    type Error = Error;
+    type Future =
+        EitherFuture<CustomResponderFut<T::Future>, FutureResult<Response, Error>>;
+
+    fn respond_to(self, req: &HttpRequest) -> Self::Future {
+        if let Some(e) = self.error {
+            return EitherFuture::B(err(e.into()));
+        }
+
+        EitherFuture::A(CustomResponderFut {
+            fut: self.responder.respond_to(req),
+            status: self.status,
+            headers: self.headers,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct CustomResponderFut<F> {
+    fut: F,
+    status: Option<StatusCode>,
+    headers: Option<HeaderMap>,
+}
+
+impl<F> Future for CustomResponderFut<F>
+where
+    F: Future<Item = Response>,
+    F::Error: Into<Error>,
+{
+    type Item = Response;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut res = try_ready!(self.fut.poll().map_err(|e| e.into()));
+
+        if let Some(status) = self.status {
+            *res.status_mut() = status;
+        }
+        if let Some(ref headers) = self.headers {
+            for key in headers.keys() {
+                res.headers_mut().remove(key);
+            }
+            for (key, value) in headers.iter() {
+                res.headers_mut().append(key.clone(), value.clone());
+            }
+        }
+        Ok(Async::Ready(res))
+    }
 }
 
 impl Responder for Response {
@@ -138,6 +283,136 @@ impl Responder for BytesMut {
     }
 }
 
+/// Streaming responder for a stream of bytes
+///
+/// By default response is sent with chunked transfer encoding. If content
+/// length is set, stream must produce exactly that number of bytes.
+/// If stream fails, or produces a different number of bytes, connection
+/// is aborted, so the client never sees truncated response as a complete one.
+///
+/// ```rust
+/// use actix_web2::{Error, Streaming};
+/// use bytes::Bytes;
+/// use futures::stream;
+///
+/// fn export() -> Streaming<impl futures::Stream<Item = Bytes, Error = Error>> {
+///     let rows = (0..1000).map(|i| Bytes::from(format!("{},row\n", i)));
+///     Streaming::new(stream::iter_ok(rows)).content_type("text/csv")
+/// }
+/// # fn main() {}
+/// ```
+pub struct Streaming<S> {
+    stream: S,
+    content_type: Option<HeaderValue>,
+    length: Option<u64>,
+    error: Option<HttpError>,
+}
+
+impl<S, E> Streaming<S>
+where
+    S: Stream<Item = Bytes, Error = E>,
+    E: Into<Error>,
+{
+    /// Create streaming responder
+    pub fn new(stream: S) -> Self {
+        Streaming {
+            stream,
+            content_type: None,
+            length: None,
+            error: None,
+        }
+    }
+
+    /// Set response content type
+    pub fn content_type<V>(mut self, value: V) -> Self
+    where
+        HeaderValue: HttpTryFrom<V>,
+    {
+        match HeaderValue::try_from(value) {
+            Ok(value) => self.content_type = Some(value),
+            Err(e) => self.error = Some(e.into()),
+        }
+        self
+    }
+
+    /// Set response content length
+    pub fn content_length(mut self, length: u64) -> Self {
+        self.length = Some(length);
+        self
+    }
+}
+
+impl<S, E> Responder for Streaming<S>
+where
+    S: Stream<Item = Bytes, Error = E> + 'static,
+    E: Into<Error> + 'static,
+{
+    type Error = Error;
+    type Future = FutureResult<Response, Error>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        if let Some(e) = self.error {
+            return err(e.into());
+        }
+
+        let mut resp = Response::build(StatusCode::OK);
+        if let Some(ct) = self.content_type {
+            resp.header(CONTENT_TYPE, ct);
+        }
+
+        match self.length {
+            Some(size) => ok(resp.body(Body::Message(Box::new(SizedStream {
+                size,
+                written: 0,
+                stream: self.stream,
+            })))),
+            None => ok(resp.streaming(self.stream)),
+        }
+    }
+}
+
+/// Stream body with known size
+struct SizedStream<S> {
+    size: u64,
+    written: u64,
+    stream: S,
+}
+
+impl<S, E> MessageBody for SizedStream<S>
+where
+    S: Stream<Item = Bytes, Error = E>,
+    E: Into<Error>,
+{
+    fn length(&self) -> BodyLength {
+        BodyLength::Sized64(self.size)
+    }
+
+    fn poll_next(&mut self) -> Poll<Option<Bytes>, Error> {
+        match self.stream.poll().map_err(|e| e.into())? {
+            Async::Ready(Some(chunk)) => {
+                self.written += chunk.len() as u64;
+                if self.written > self.size {
+                    Err(ErrorInternalServerError(
+                        "Stream is longer than content length",
+                    ))
+                } else {
+                    Ok(Async::Ready(Some(chunk)))
+                }
+            }
+            Async::Ready(None) => {
+                if self.written < self.size {
+                    Err(ErrorInternalServerError(
+                        "Stream is shorter than content length",
+                    ))
+                } else {
+                    Ok(Async::Ready(None))
+                }
+            }
+            Async::NotReady => Ok(Async::NotReady),
+        }
+    }
+}
+
 /// Combines two different responder types into a single type
 ///
 /// ```rust,ignore
@@ -257,3 +532,160 @@ where
         Ok(self.0.poll().map_err(|e| e.into())?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use actix_http::error::ErrorBadRequest;
+    use actix_http::http::header;
+    use actix_http::test::TestRequest;
+    use actix_http::Extensions;
+    use actix_router::{Path, Url};
+    use futures::stream;
+
+    use super::*;
+
+    fn request() -> HttpRequest {
+        let (head, _) = TestRequest::default().finish().into_parts();
+        let path = Path::new(Url::new(head.uri.clone()));
+        HttpRequest::new(head, path, Rc::new(Extensions::new()))
+    }
+
+    #[test]
+    fn test_with_status() {
+        let res = "test"
+            .with_status(StatusCode::CREATED)
+            .respond_to(&request())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let res = Response::BadRequest()
+            .finish()
+            .with_status(StatusCode::CONFLICT)
+            .respond_to(&request())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_with_header() {
+        let res = Response::Ok()
+            .header("x-version", "1")
+            .header("x-version", "2")
+            .content_type("text/plain")
+            .finish()
+            .with_header("x-version", "3")
+            .with_header(header::CONTENT_TYPE, "application/json")
+            .respond_to(&request())
+            .wait()
+            .unwrap();
+
+        let values: Vec<_> = res.headers().get_all("x-version").iter().collect();
+        assert_eq!(values, vec!["3"]);
+        let values: Vec<_> =
+            res.headers().get_all(header::CONTENT_TYPE).iter().collect();
+        assert_eq!(values, vec!["application/json"]);
+    }
+
+    #[test]
+    fn test_with_header_error() {
+        let res = "test"
+            .with_header("x-version", "1\n2")
+            .respond_to(&request())
+            .wait();
+        assert!(res.is_err());
+
+        let res = "test"
+            .with_header("x version", "1")
+            .respond_to(&request())
+            .wait();
+        assert!(res.is_err());
+    }
+
+    fn chunks(chunks: &[&'static str]) -> impl Stream<Item = Bytes, Error = Error> {
+        let chunks: Vec<_> = chunks
+            .iter()
+            .map(|c| Bytes::from_static(c.as_bytes()))
+            .collect();
+        stream::iter_ok(chunks)
+    }
+
+    #[test]
+    fn test_streaming() {
+        let res = Streaming::new(chunks(&["hello", " world"]))
+            .content_type("text/plain")
+            .respond_to(&request())
+            .wait()
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "text/plain");
+        match res.body().length() {
+            BodyLength::Stream => (),
+            _ => panic!("response is expected to be chunked"),
+        }
+
+        let res = Streaming::new(chunks(&["hello", " world"]))
+            .content_length(11)
+            .respond_to(&request())
+            .wait()
+            .unwrap();
+        match res.body().length() {
+            BodyLength::Sized64(11) => (),
+            _ => panic!("content length is expected"),
+        }
+
+        let res = Streaming::new(chunks(&[]))
+            .content_type("text\nplain")
+            .respond_to(&request())
+            .wait();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_sized_stream() {
+        let mut body = SizedStream {
+            size: 11,
+            written: 0,
+            stream: chunks(&["hello", " world"]),
+        };
+        assert_eq!(
+            body.poll_next().unwrap(),
+            Async::Ready(Some(Bytes::from_static(b"hello")))
+        );
+        assert_eq!(
+            body.poll_next().unwrap(),
+            Async::Ready(Some(Bytes::from_static(b" world")))
+        );
+        assert_eq!(body.poll_next().unwrap(), Async::Ready(None));
+
+        // stream is longer than content length
+        let mut body = SizedStream {
+            size: 8,
+            written: 0,
+            stream: chunks(&["hello", " world"]),
+        };
+        assert!(body.poll_next().is_ok());
+        assert!(body.poll_next().is_err());
+
+        // stream is shorter than content length
+        let mut body = SizedStream {
+            size: 16,
+            written: 0,
+            stream: chunks(&["hello", " world"]),
+        };
+        assert!(body.poll_next().is_ok());
+        assert!(body.poll_next().is_ok());
+        assert!(body.poll_next().is_err());
+
+        // stream errors are passed through
+        let mut body = SizedStream {
+            size: 5,
+            written: 0,
+            stream: stream::once::<Bytes, _>(Err(ErrorBadRequest("failed"))),
+        };
+        assert!(body.poll_next().is_err());
+    }
+}
@@ -27,6 +27,11 @@ impl<P> ServiceRequest<P> {
         }
     }
 
+    /// Construct service request from request and payload
+    pub(crate) fn from_parts(req: HttpRequest, payload: Payload<P>) -> Self {
+        ServiceRequest { req, payload }
+    }
+
     #[inline]
     pub fn request(&self) -> &HttpRequest {
         &self.req
@@ -49,6 +54,19 @@ impl<P> ServiceRequest<P> {
         ServiceResponse::new(self.req, err.into().into())
     }
 
+    /// Mutable reference to the request's headers, `None` if request is
+    /// shared
+    #[inline]
+    pub(crate) fn headers_mut(&mut self) -> Option<&mut HeaderMap> {
+        self.req.head_mut().map(|head| &mut head.headers)
+    }
+
+    /// Replace request's payload
+    #[inline]
+    pub(crate) fn set_payload(&mut self, payload: Payload<P>) {
+        self.payload = payload;
+    }
+
     #[inline]
     pub fn match_info_mut(&mut self) -> &mut Path<Url> {
         &mut self.req.path
@@ -0,0 +1,341 @@
+//! Server-Sent Events
+use std::time::{Duration, Instant};
+
+use actix_http::error::{Error, ErrorInternalServerError};
+use actix_http::http::header::{CACHE_CONTROL, CONTENT_ENCODING};
+use actix_http::http::StatusCode;
+use actix_http::Response;
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::future::{ok, FutureResult};
+use futures::{Async, Future, Poll, Stream};
+use tokio_timer::Delay;
+
+use crate::handler::FromRequest;
+use crate::request::HttpRequest;
+use crate::responder::Responder;
+use crate::service::ServiceRequest;
+
+/// Single server-sent event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: String,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// Create event with data.
+    ///
+    /// Multi-line data is sent as multiple `data` fields.
+    pub fn new<T: Into<String>>(data: T) -> Event {
+        Event {
+            id: None,
+            event: None,
+            data: data.into(),
+            retry: None,
+        }
+    }
+
+    /// Set event id, client sends it back with `Last-Event-ID` header
+    /// on reconnect.
+    pub fn id<T: Into<String>>(mut self, id: T) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Set event name
+    pub fn event<T: Into<String>>(mut self, event: T) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Set client's reconnection time
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Encode event to the `text/event-stream` format
+    fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(self.data.len() + 64);
+
+        if let Some(ref id) = self.id {
+            put_field(&mut buf, "id", id);
+        }
+        if let Some(ref event) = self.event {
+            put_field(&mut buf, "event", event);
+        }
+        if let Some(retry) = self.retry {
+            let millis = retry.as_secs() * 1000 + u64::from(retry.subsec_millis());
+            put_field(&mut buf, "retry", &millis.to_string());
+        }
+        for line in self.data.split('\n') {
+            put_field(&mut buf, "data", line.trim_end_matches('\r'));
+        }
+        buf.reserve(1);
+        buf.put_u8(b'\n');
+        buf.freeze()
+    }
+}
+
+/// Write single field line, line breaks are not allowed in values
+fn put_field(buf: &mut BytesMut, name: &str, value: &str) {
+    let value = value.replace(|c| c == '\r' || c == '\n', "");
+    buf.reserve(name.len() + value.len() + 3);
+    buf.put_slice(name.as_bytes());
+    buf.put_slice(b": ");
+    buf.put_slice(value.as_bytes());
+    buf.put_u8(b'\n');
+}
+
+/// `text/event-stream` responder
+///
+/// Events are sent as soon as the stream yields them. Keep-alive comment is
+/// sent if there were no events for the keep-alive interval, by default
+/// 15 seconds. Response is never compressed, so events are not held back by
+/// the `Compress` middleware. Stream error terminates the response.
+///
+/// ```rust
+/// use actix_web2::{App, Error, Event, LastEventId, Sse};
+/// use futures::{stream, Stream};
+///
+/// fn events(last_id: LastEventId) -> Sse<impl Stream<Item = Event, Error = Error>> {
+///     // resume stream after the last received event
+///     let start = last_id.0.and_then(|id| id.parse().ok()).unwrap_or(0u64);
+///
+///     Sse::new(stream::iter_ok((start..start + 10).map(|id| {
+///         Event::new(format!("tick {}", id)).id(id.to_string()).event("tick")
+///     })))
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/events", |r| r.get(events));
+/// }
+/// ```
+pub struct Sse<S> {
+    stream: S,
+    keep_alive: Option<Duration>,
+}
+
+impl<S> Sse<S>
+where
+    S: Stream<Item = Event>,
+    S::Error: Into<Error>,
+{
+    /// Create responder from a stream of events
+    pub fn new(stream: S) -> Self {
+        Sse {
+            stream,
+            keep_alive: Some(Duration::from_secs(15)),
+        }
+    }
+
+    /// Set keep-alive interval, `None` disables keep-alive comments
+    pub fn keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+}
+
+impl<S> Responder for Sse<S>
+where
+    S: Stream<Item = Event> + 'static,
+    S::Error: Into<Error>,
+{
+    type Error = Error;
+    type Future = FutureResult<Response, Error>;
+
+    fn respond_to(self, _: &HttpRequest) -> Self::Future {
+        let delay = self
+            .keep_alive
+            .map(|interval| Delay::new(Instant::now() + interval));
+
+        ok(Response::build(StatusCode::OK)
+            .content_type("text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            // disable compression
+            .header(CONTENT_ENCODING, "identity")
+            .streaming(SseStream {
+                stream: self.stream,
+                keep_alive: self.keep_alive,
+                delay,
+            }))
+    }
+}
+
+struct SseStream<S> {
+    stream: S,
+    keep_alive: Option<Duration>,
+    delay: Option<Delay>,
+}
+
+impl<S> SseStream<S> {
+    fn reset_delay(&mut self) {
+        if let (Some(interval), Some(delay)) = (self.keep_alive, self.delay.as_mut()) {
+            delay.reset(Instant::now() + interval);
+        }
+    }
+}
+
+impl<S> Stream for SseStream<S>
+where
+    S: Stream<Item = Event>,
+    S::Error: Into<Error>,
+{
+    type Item = Bytes;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<Bytes>, Error> {
+        match self.stream.poll().map_err(|e| e.into())? {
+            Async::Ready(Some(event)) => {
+                self.reset_delay();
+                return Ok(Async::Ready(Some(event.encode())));
+            }
+            Async::Ready(None) => return Ok(Async::Ready(None)),
+            Async::NotReady => (),
+        }
+
+        let expired = match self.delay {
+            Some(ref mut delay) => delay.poll().map_err(ErrorInternalServerError)?,
+            None => Async::NotReady,
+        };
+        if expired.is_ready() {
+            self.reset_delay();
+            Ok(Async::Ready(Some(Bytes::from_static(b": keep-alive\n\n"))))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+/// Extract `Last-Event-ID` header of the reconnecting client
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastEventId(pub Option<String>);
+
+impl<P> FromRequest<P> for LastEventId {
+    type Error = Error;
+    type Future = FutureResult<Self, Error>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        ok(LastEventId(
+            req.headers()
+                .get("last-event-id")
+                .and_then(|val| val.to_str().ok())
+                .map(|val| val.to_owned()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use actix_http::body::{Body, MessageBody, ResponseBody};
+    use actix_http::http::header::CONTENT_TYPE;
+    use actix_http::test::TestRequest;
+    use actix_http::Extensions;
+    use actix_router::{Path, Url};
+    use futures::{future, stream};
+
+    use super::*;
+
+    fn respond<S>(sse: Sse<S>) -> Response
+    where
+        S: Stream<Item = Event> + 'static,
+        S::Error: Into<Error>,
+    {
+        let (head, _) = TestRequest::default().finish().into_parts();
+        let path = Path::new(Url::new(head.uri.clone()));
+        let req = HttpRequest::new(head, path, Rc::new(Extensions::new()));
+        sse.respond_to(&req).wait().unwrap()
+    }
+
+    fn take_body(res: Response) -> ResponseBody<Body> {
+        let mut body = None;
+        res.map_body(|_, b| {
+            body = Some(b);
+            ResponseBody::Other(Body::Empty)
+        });
+        body.unwrap()
+    }
+
+    #[test]
+    fn test_event_encode() {
+        assert_eq!(
+            Event::new("hello").encode(),
+            Bytes::from_static(b"data: hello\n\n")
+        );
+
+        let event = Event::new("line 1\r\nline 2")
+            .id("42")
+            .event("up\ndate")
+            .retry(Duration::from_millis(1500));
+        assert_eq!(
+            event.encode(),
+            Bytes::from_static(
+                b"id: 42\nevent: update\nretry: 1500\ndata: line 1\ndata: line 2\n\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_responder() {
+        let events = stream::iter_ok::<_, Error>(vec![
+            Event::new("first").id("1"),
+            Event::new("second").id("2"),
+        ]);
+        let res = respond(Sse::new(events).keep_alive(None));
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "identity");
+        assert_eq!(res.headers().get(CACHE_CONTROL).unwrap(), "no-cache");
+
+        let mut body = take_body(res);
+        let mut buf = BytesMut::new();
+        while let Async::Ready(Some(chunk)) = body.poll_next().unwrap() {
+            buf.extend_from_slice(&chunk);
+        }
+        assert_eq!(
+            buf.freeze(),
+            Bytes::from_static(b"id: 1\ndata: first\n\nid: 2\ndata: second\n\n")
+        );
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let chunk = actix_rt::System::new("test")
+            .block_on(future::lazy(|| {
+                // stream that never yields events
+                let events = stream::poll_fn(|| -> Poll<Option<Event>, Error> {
+                    Ok(Async::NotReady)
+                });
+                let res = respond(
+                    Sse::new(events).keep_alive(Some(Duration::from_millis(10))),
+                );
+                let mut body = take_body(res);
+                future::poll_fn(move || body.poll_next())
+            }))
+            .unwrap();
+        assert_eq!(chunk, Some(Bytes::from_static(b": keep-alive\n\n")));
+    }
+
+    #[test]
+    fn test_last_event_id() {
+        let req = TestRequest::with_header("last-event-id", "42").finish();
+        let path = Path::new(Url::new(req.uri().clone()));
+        let mut req = ServiceRequest::new(path, req, Rc::new(Extensions::new()));
+        let id = LastEventId::from_request(&mut req).wait().unwrap();
+        assert_eq!(id, LastEventId(Some("42".to_owned())));
+
+        let req = TestRequest::default().finish();
+        let path = Path::new(Url::new(req.uri().clone()));
+        let mut req = ServiceRequest::new(path, req, Rc::new(Extensions::new()));
+        let id = LastEventId::from_request(&mut req).wait().unwrap();
+        assert_eq!(id, LastEventId(None));
+    }
+}
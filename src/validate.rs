@@ -0,0 +1,395 @@
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::{fmt, slice};
+
+use actix_http::error::{Error, ResponseError};
+use actix_http::http::StatusCode;
+use actix_http::Response;
+use futures::Future;
+use serde_json::json;
+
+use crate::extractor::load_config;
+use crate::handler::FromRequest;
+use crate::request::HttpRequest;
+use crate::service::ServiceRequest;
+
+/// Trait implemented by types that could be validated after extraction.
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{Validate, ValidationErrors};
+///
+/// #[derive(Deserialize)]
+/// struct Info {
+///     username: String,
+///     age: u32,
+/// }
+///
+/// impl Validate for Info {
+///     fn validate(&self) -> Result<(), ValidationErrors> {
+///         let mut errors = ValidationErrors::new();
+///         if self.username.is_empty() {
+///             errors.add("username", "must not be empty");
+///         }
+///         if self.age > 150 {
+///             errors.add("age", "must be less than 150");
+///         }
+///         errors.into_result()
+///     }
+/// }
+/// # fn main() {}
+/// ```
+pub trait Validate {
+    /// Validate value, return all found field errors.
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// Validation error of a single field
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    field: String,
+    message: String,
+}
+
+impl FieldError {
+    /// Name of the invalid field
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// Error description
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// List of field validation errors.
+///
+/// `ValidationErrors` generates *400 Bad Request* response
+/// with json body:
+///
+/// ```json
+/// {"errors": [{"field": "username", "message": "must not be empty"}]}
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    /// Create empty errors list
+    pub fn new() -> Self {
+        ValidationErrors::default()
+    }
+
+    /// Add field error
+    pub fn add<F, M>(&mut self, field: F, message: M) -> &mut Self
+    where
+        F: Into<String>,
+        M: Into<String>,
+    {
+        self.errors.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Check if errors list is empty
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Iterate over field errors
+    pub fn iter(&self) -> slice::Iter<FieldError> {
+        self.errors.iter()
+    }
+
+    /// Convert to a validation result, `Ok` if errors list is empty
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Validation failed:")?;
+        for err in &self.errors {
+            write!(f, " {}: {};", err.field, err.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Return `BadRequest` with list of field errors
+impl ResponseError for ValidationErrors {
+    fn error_response(&self) -> Response {
+        let errors: Vec<_> = self
+            .errors
+            .iter()
+            .map(|e| json!({"field": e.field, "message": e.message}))
+            .collect();
+
+        Response::build(StatusCode::BAD_REQUEST)
+            .content_type("application/json")
+            .body(json!({ "errors": errors }).to_string())
+    }
+}
+
+/// Extractor wrapper that validates extracted value.
+///
+/// Wrapped extractor must dereference to a type that implements
+/// [`Validate`](trait.Validate.html) trait, i.e. `Json<T>`, `Form<T>`,
+/// `Query<T>` or `Path<T>`. If validation fails, request is rejected with
+/// the response generated by [**ValidationConfig**](dev/struct.ValidationConfig.html)
+/// error handler.
+///
+/// ## Example
+///
+/// ```rust
+/// #[macro_use] extern crate serde_derive;
+/// use actix_web2::{App, Json, Validate, Validated, ValidationErrors};
+///
+/// #[derive(Deserialize)]
+/// struct Info {
+///     username: String,
+/// }
+///
+/// impl Validate for Info {
+///     fn validate(&self) -> Result<(), ValidationErrors> {
+///         let mut errors = ValidationErrors::new();
+///         if self.username.is_empty() {
+///             errors.add("username", "must not be empty");
+///         }
+///         errors.into_result()
+///     }
+/// }
+///
+/// /// handler get called only if `Info` is valid
+/// fn index(info: Validated<Json<Info>>) -> String {
+///     format!("Welcome {}!", info.username)
+/// }
+///
+/// fn main() {
+///     let app = App::new().resource("/index.html", |r| r.post(index));
+/// }
+/// ```
+pub struct Validated<T>(pub T);
+
+impl<T> Validated<T> {
+    /// Deconstruct to an inner extractor
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Validated<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Validated<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Validated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T, P> FromRequest<P> for Validated<T>
+where
+    T: FromRequest<P> + Deref + 'static,
+    T::Target: Validate,
+    T::Future: 'static,
+{
+    type Error = Error;
+    type Future = Box<Future<Item = Self, Error = Error>>;
+
+    #[inline]
+    fn from_request(req: &mut ServiceRequest<P>) -> Self::Future {
+        let req2 = req.clone();
+        Box::new(
+            T::from_request(req)
+                .map_err(|e| e.into())
+                .and_then(move |item| match item.validate() {
+                    Ok(_) => Ok(Validated(item)),
+                    Err(errors) => {
+                        let cfg = load_config::<ValidationConfig>(&req2);
+                        Err((*cfg.ehandler)(errors, &req2))
+                    }
+                }),
+        )
+    }
+}
+
+/// Validation configuration
+///
+/// ```rust
+/// use actix_http::error;
+/// use actix_web2::{dev, App, HttpResponse};
+///
+/// fn main() {
+///     let app = App::new().config(|cfg: &mut dev::ValidationConfig| {
+///         cfg.error_handler(|errors, _| {  // <- create custom error response
+///             error::InternalError::from_response(
+///                 errors, HttpResponse::BadRequest().finish()).into()
+///         });
+///     });
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ValidationConfig {
+    ehandler: Rc<Fn(ValidationErrors, &HttpRequest) -> Error>,
+}
+
+impl ValidationConfig {
+    /// Set custom error handler
+    pub fn error_handler<F>(&mut self, f: F) -> &mut Self
+    where
+        F: Fn(ValidationErrors, &HttpRequest) -> Error + 'static,
+    {
+        self.ehandler = Rc::new(f);
+        self
+    }
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            ehandler: Rc::new(|e, _| e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::body::{Body, ResponseBody};
+    use actix_http::error::InternalError;
+    use actix_http::http::header;
+    use actix_http::test::TestRequest;
+    use actix_http::Request;
+    use actix_service::{IntoNewService, NewService, Service};
+    use bytes::Bytes;
+    use serde_derive::Deserialize;
+
+    use super::*;
+    use crate::extractor::Json;
+    use crate::service::ServiceResponse;
+    use crate::App;
+
+    #[derive(Deserialize)]
+    struct Info {
+        username: String,
+    }
+
+    impl Validate for Info {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+            if self.username.is_empty() {
+                errors.add("username", "must not be empty");
+            }
+            errors.into_result()
+        }
+    }
+
+    fn index(info: Validated<Json<Info>>) -> String {
+        format!("Welcome {}!", info.username)
+    }
+
+    fn call<S>(srv: &mut S, body: &'static str) -> (StatusCode, Bytes)
+    where
+        S: Service<Request = Request, Response = ServiceResponse, Error = ()>,
+    {
+        let req = TestRequest::with_header(header::CONTENT_TYPE, "application/json")
+            .set_payload(Bytes::from_static(body.as_bytes()))
+            .finish();
+        let res = srv.call(req).wait().unwrap();
+        let body = match res.response().body() {
+            ResponseBody::Body(Body::Bytes(b)) | ResponseBody::Other(Body::Bytes(b)) => {
+                b.clone()
+            }
+            _ => Bytes::new(),
+        };
+        (res.status(), body)
+    }
+
+    #[test]
+    fn test_validated() {
+        let mut srv = App::new()
+            .resource("/", |r| r.to(index))
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap();
+
+        let (status, body) = call(&mut srv, r#"{"username": "john"}"#);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, Bytes::from_static(b"Welcome john!"));
+
+        let (status, body) = call(&mut srv, r#"{"username": ""}"#);
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let val: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(val["errors"][0]["field"], "username");
+        assert_eq!(val["errors"][0]["message"], "must not be empty");
+    }
+
+    #[test]
+    fn test_validated_error_handler() {
+        let mut srv = App::new()
+            .config(|cfg: &mut ValidationConfig| {
+                cfg.error_handler(|errors, _| {
+                    InternalError::from_response(errors, Response::Conflict().finish())
+                        .into()
+                });
+            })
+            .resource("/", |r| r.to(index))
+            .into_new_service()
+            .new_service()
+            .wait()
+            .unwrap();
+
+        assert_eq!(call(&mut srv, r#"{"username": "john"}"#).0, StatusCode::OK);
+        assert_eq!(
+            call(&mut srv, r#"{"username": ""}"#).0,
+            StatusCode::CONFLICT
+        );
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let mut errors = ValidationErrors::new();
+        assert!(errors.clone().into_result().is_ok());
+
+        errors
+            .add("username", "must not be empty")
+            .add("age", "too old");
+        assert_eq!(errors.iter().count(), 2);
+        assert_eq!(errors.iter().next().unwrap().field(), "username");
+        assert_eq!(
+            format!("{}", errors),
+            "Validation failed: username: must not be empty; age: too old;"
+        );
+
+        let resp = errors.error_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        match resp.body() {
+            ResponseBody::Body(Body::Bytes(b)) | ResponseBody::Other(Body::Bytes(b)) => {
+                let val: serde_json::Value = serde_json::from_slice(b).unwrap();
+                assert_eq!(val["errors"][1]["field"], "age");
+                assert_eq!(val["errors"][1]["message"], "too old");
+            }
+            _ => panic!(),
+        }
+    }
+}